@@ -9,19 +9,23 @@ use libdiffsitter::cli::Args;
 use libdiffsitter::config::Config;
 use libdiffsitter::config::APP_NAME;
 use libdiffsitter::console_utils;
-use libdiffsitter::diff;
-use libdiffsitter::generate_ast_vector_data;
+use libdiffsitter::differ;
+use libdiffsitter::dir_diff::pair_files;
+use libdiffsitter::git::{self, GitFileDiff};
 use libdiffsitter::parse::generate_language;
 use libdiffsitter::parse::lang_name_from_file_ext;
 #[cfg(feature = "static-grammar-libs")]
 use libdiffsitter::parse::SUPPORTED_LANGUAGES;
-use libdiffsitter::render::{DisplayData, DocumentDiffData, Renderer};
+use libdiffsitter::render::{RenderState, Renderer, Renderers};
+use libdiffsitter::STDIN_PATH;
+use libdiffsitter::{Differ, Source};
 use log::{debug, info, warn, LevelFilter};
 use serde_json as json;
 use std::{
-    io,
+    fs,
+    io::{self, Write},
     path::Path,
-    process::{Child, Command},
+    process::{Command, ExitCode},
 };
 
 #[cfg(feature = "better-build-info")]
@@ -34,12 +38,30 @@ use jemallocator::Jemalloc;
 #[global_allocator]
 static GLOBAL: Jemalloc = Jemalloc;
 
+/// The exit code when the inputs have semantic differences, which matches `diff`
+const EXIT_DIFFERENCES: u8 = 1;
+
+/// The exit code when an error is encountered, which matches `diff`
+const EXIT_ERROR: u8 = 2;
+
 /// Return an instance of [Config] from a config file path (or the inferred default path)
 ///
 /// If a config path isn't provided or there is some other failure, fall back to the default
 /// config. This will error out if a config is found but is found to be an invalid config.
 fn derive_config(args: &Args) -> Result<Config> {
-    Ok(Config::new_from_args(args)?)
+    let mut config = Config::new_from_args(args)?;
+
+    // Options from the command line take precedence over the config
+    if let Some(engine) = args.engine {
+        config.diff.engine = engine;
+    }
+    if args.detect_moves {
+        config.diff.detect_moves = true;
+    }
+    if args.ignore_comments {
+        config.input_processing.ignore_comments = true;
+    }
+    Ok(config)
 }
 
 /// Check if the input files are supported by this program.
@@ -50,17 +72,33 @@ fn derive_config(args: &Args) -> Result<Config> {
 ///
 /// This is used to determine whether the program should fall back to another diff utility.
 fn are_input_files_supported(args: &Args, config: &Config) -> bool {
-    let paths = [&args.old, &args.new];
+    are_paths_supported(
+        &[args.old.as_deref(), args.new.as_deref()],
+        args.file_type.as_deref(),
+        config,
+    )
+}
 
+/// Check if every path in `paths` can be parsed with one of our grammars.
+///
+/// A missing path is never supported. `file_type` is the user's language override, if any.
+fn are_paths_supported(paths: &[Option<&Path>], file_type: Option<&str>, config: &Config) -> bool {
     // If there's a user override at the command line, that takes priority over everything else if
     // it corresponds to a valid grammar/language string.
-    if let Some(file_type) = &args.file_type {
+    if let Some(file_type) = file_type {
         return generate_language(file_type, &config.grammar).is_ok();
     }
 
+    // A path without an extension (like stdin) is parsed with the language of the other path, so
+    // it's supported as long as the other path is.
+    let any_path_has_ext = paths
+        .iter()
+        .flatten()
+        .any(|path| path.extension().is_some());
+
     // For each path, attempt to create a parser for that given extension, checking for any
     // possible overrides.
-    paths.into_iter().all(|path| match path {
+    paths.iter().all(|path| match path {
         None => {
             warn!("Missing a file. You need two files to make a diff.");
             false
@@ -68,6 +106,13 @@ fn are_input_files_supported(args: &Args, config: &Config) -> bool {
         Some(path) => {
             debug!("Checking if {} can be parsed", path.display());
             match path.extension() {
+                None if any_path_has_ext => {
+                    debug!(
+                        "Using the filetype of the other file for {}",
+                        path.display()
+                    );
+                    true
+                }
                 None => {
                     warn!("No filetype deduced for {}", path.display());
                     false
@@ -91,52 +136,364 @@ fn are_input_files_supported(args: &Args, config: &Config) -> bool {
     })
 }
 
-/// Take the diff of two files
-fn run_diff(args: Args, config: Config) -> Result<()> {
+/// Get the renderer the user selected, with any overrides from the command line applied.
+fn get_renderer(args: &Args, config: &Config) -> Result<Renderers> {
+    let render_config = config.formatting.clone();
+    let render_param = args.renderer.clone();
+    let mut renderer = render_config.get_renderer(render_param)?;
+
+    // Options from the command line take precedence over the config
+    if let Some(context_lines) = args.context_lines {
+        match &mut renderer {
+            Renderers::Unified(unified) => unified.context_lines = context_lines,
+            Renderers::Patch(patch) => patch.context_lines = context_lines,
+            _ => (),
+        }
+    }
+    if let Renderers::Unified(unified) = &mut renderer {
+        unified.line_numbers |= args.line_numbers;
+    }
+    Ok(renderer)
+}
+
+/// Create a differ with the user's config and command line options.
+fn get_differ(args: &Args, config: &Config) -> Differ {
+    let differ = Differ::new(config);
+    match &args.file_type {
+        Some(file_type) => differ.with_language(file_type),
+        None => differ,
+    }
+}
+
+/// Take the diff of two files, returning whether they differ
+fn run_diff(args: Args, config: Config) -> Result<bool> {
     // Check whether we can get the renderer up front. This is more ergonomic than running the diff
     // and then informing the user their renderer choice is incorrect/that the config is invalid.
-    let render_config = config.formatting;
-    let render_param = args.renderer;
-    let renderer = render_config.get_renderer(render_param)?;
+    let renderer = get_renderer(&args, &config)?;
+    let differ = get_differ(&args, &config);
+    let path_a = args.old.as_deref().unwrap();
+    let path_b = args.new.as_deref().unwrap();
 
-    let file_type = args.file_type.as_deref();
-    let path_a = args.old.as_ref().unwrap();
-    let path_b = args.new.as_ref().unwrap();
-
-    // This looks a bit weird because the ast vectors and some other data reference data in the
-    // AstVectorData structs. Because of that, we can't make a function that generates the ast
-    // vectors in one shot.
-
-    let ast_data_a = generate_ast_vector_data(path_a.clone(), file_type, &config.grammar)?;
-    let ast_data_b = generate_ast_vector_data(path_b.clone(), file_type, &config.grammar)?;
-    let diff_vec_a = config
-        .input_processing
-        .process(&ast_data_a.tree, &ast_data_a.text);
-    let diff_vec_b = config
-        .input_processing
-        .process(&ast_data_b.tree, &ast_data_b.text);
-
-    let hunks = diff::compute_edit_script(&diff_vec_a, &diff_vec_b)?;
-    let params = DisplayData {
-        hunks,
-        old: DocumentDiffData {
-            filename: &ast_data_a.path.to_string_lossy(),
-            text: &ast_data_a.text,
-        },
-        new: DocumentDiffData {
-            filename: &ast_data_b.path.to_string_lossy(),
-            text: &ast_data_b.text,
-        },
-    };
     // Use a buffered terminal instead of a normal unbuffered terminal so we can amortize the cost
     // of printing. It doesn't really matter how frequently the terminal prints to stdout because
     // the user just cares about the output at the end, we don't care about how frequently the
     // terminal does partial updates or anything like that. If the user is curious about progress,
     // they can enable logging and see when hunks are processed and written to the buffer.
     let mut buf_writer = Term::buffered_stdout();
-    let term_info = buf_writer.clone();
-    renderer.render(&mut buf_writer, &params, Some(&term_info))?;
+    let mut state = RenderState::default();
+    if !args.brief {
+        renderer.begin(&mut buf_writer, &mut state)?;
+    }
+    let differs = diff_files(
+        Some(path_a),
+        Some(path_b),
+        &differ,
+        &renderer,
+        args.brief,
+        &mut buf_writer,
+        &mut state,
+    )?;
+    if !args.brief {
+        renderer.end(&mut buf_writer, &mut state)?;
+    }
+    buf_writer.flush()?;
+    Ok(differs)
+}
+
+/// Take the diff of every file in two directories, returning whether any of the files differ
+///
+/// Files are paired up by their path relative to each directory. Files that are identical in both
+/// directories are skipped. Files that only exist in one of the directories are diffed against an
+/// empty document if they're supported, otherwise they're reported like `diff -r` does.
+fn run_dir_diff(args: Args, config: Config) -> Result<bool> {
+    let renderer = get_renderer(&args, &config)?;
+    let differ = get_differ(&args, &config);
+    let file_type = args.file_type.as_deref();
+    let old_dir = args.old.as_deref().unwrap();
+    let new_dir = args.new.as_deref().unwrap();
+    let mut buf_writer = Term::buffered_stdout();
+    let mut differs = false;
+    let mut state = RenderState::default();
+    if !args.brief {
+        renderer.begin(&mut buf_writer, &mut state)?;
+    }
+
+    for pair in pair_files(old_dir, new_dir)? {
+        debug!("Processing {}", pair.relative_path.display());
+
+        if let (Some(old), Some(new)) = (&pair.old, &pair.new) {
+            if fs::read(old)? == fs::read(new)? {
+                debug!("{} is unchanged", pair.relative_path.display());
+                continue;
+            }
+        }
+        let old = pair.old.as_deref();
+        let new = pair.new.as_deref();
+        // Missing files don't need to be parsed, so they don't affect whether a pair is supported
+        let paths: Vec<_> = [old, new].into_iter().filter(Option::is_some).collect();
+
+        if are_paths_supported(&paths, file_type, &config) {
+            match diff_files(
+                old,
+                new,
+                &differ,
+                &renderer,
+                args.brief,
+                &mut buf_writer,
+                &mut state,
+            ) {
+                Ok(file_differs) => {
+                    differs |= file_differs;
+                    continue;
+                }
+                // Files that can't be loaded, like files that aren't valid UTF-8, are handled like
+                // unsupported files, so the rest of the directory is still diffed
+                Err(e) if is_loading_error(&e) => info!("{e:#}"),
+                Err(e) => return Err(e),
+            }
+        }
+        match (old, new, &config.fallback_cmd) {
+            // The fallback would display the diff, which we don't want in brief mode or in the
+            // middle of structured output
+            (Some(old), Some(new), Some(cmd)) if !args.brief && !renderer.is_structured() => {
+                info!(
+                    "{} is not supported, using the diff fallback",
+                    pair.relative_path.display()
+                );
+                // The fallback writes directly to stdout, so we need to flush what we have so far
+                // to keep the output in order.
+                buf_writer.flush()?;
+                differs |= diff_fallback(cmd, old, new)?;
+            }
+            (Some(old), Some(new), _) => {
+                differs = true;
+                write_message(
+                    &mut buf_writer,
+                    &renderer,
+                    args.brief,
+                    &format!("Files {} and {} differ", old.display(), new.display()),
+                )?;
+            }
+            (Some(path), None, _) | (None, Some(path), _) => {
+                differs = true;
+                write_message(
+                    &mut buf_writer,
+                    &renderer,
+                    args.brief,
+                    &format!(
+                        "Only in {}: {}",
+                        path.parent().unwrap_or(path).display(),
+                        path.file_name().unwrap_or_default().to_string_lossy()
+                    ),
+                )?;
+            }
+            (None, None, _) => unreachable!("a file pair always has at least one file"),
+        }
+    }
+    if !args.brief {
+        renderer.end(&mut buf_writer, &mut state)?;
+    }
+    buf_writer.flush()?;
+    Ok(differs)
+}
+
+/// Whether an error from diffing a pair of files means that one of the files couldn't be loaded,
+/// rather than that the diff couldn't be rendered.
+fn is_loading_error(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<differ::Error>(),
+        Some(differ::Error::Io(_) | differ::Error::Loading(_))
+    )
+}
+
+/// The name that's displayed for a document that doesn't exist
+const MISSING_FILE_NAME: &str = "/dev/null";
+
+/// Diff two files and render the results to `writer`, returning whether the files differ.
+///
+/// If one of the files is missing, it's treated as an empty document, so the diff shows the entire
+/// file as added or removed. If `brief` is set, only a line saying that the files differ is
+/// written instead of the diff.
+fn diff_files(
+    old: Option<&Path>,
+    new: Option<&Path>,
+    differ: &Differ,
+    renderer: &Renderers,
+    brief: bool,
+    writer: &mut Term,
+    state: &mut RenderState,
+) -> Result<bool> {
+    let source = |path: Option<&Path>| {
+        path.map_or_else(
+            || Source::Text {
+                name: MISSING_FILE_NAME.into(),
+                text: String::new(),
+            },
+            Source::from_path,
+        )
+    };
+    let session = differ.diff(source(old), source(new))?;
+    if brief {
+        let differs = session.has_changes()?;
+        if differs {
+            writeln!(
+                writer,
+                "Files {} and {} differ",
+                session.old_document().path.display(),
+                session.new_document().path.display()
+            )?;
+        }
+        return Ok(differs);
+    }
+    let term_info = writer.clone();
+    Ok(session.render_part(renderer, writer, Some(&term_info), state)?)
+}
+
+/// Diff files from git, returning whether any of the files differ
+///
+/// If `revisions` is supplied, this diffs every file that changed between the two revisions.
+/// Otherwise this diffs the file described by the arguments git passes to external diff programs.
+fn run_git_diff(
+    revisions: Option<&[String]>,
+    external_diff_args: &[String],
+    args: &Args,
+    config: &Config,
+) -> Result<bool> {
+    let renderer = get_renderer(args, config)?;
+    let file_diffs = match revisions {
+        Some([old_rev, new_rev]) => git::diff_revisions(old_rev, new_rev)?,
+        Some(revisions) => anyhow::bail!("Expected two revisions, got {}", revisions.len()),
+        None => vec![GitFileDiff::from_external_diff_args(external_diff_args)?],
+    };
+    let mut buf_writer = Term::buffered_stdout();
+    let mut differs = false;
+    let mut state = RenderState::default();
+    if !args.brief {
+        renderer.begin(&mut buf_writer, &mut state)?;
+    }
+
+    for file_diff in &file_diffs {
+        differs |= diff_git_file(
+            file_diff,
+            args.file_type.as_deref(),
+            config,
+            &renderer,
+            args.brief,
+            &mut buf_writer,
+            &mut state,
+        )?;
+    }
+    if !args.brief {
+        renderer.end(&mut buf_writer, &mut state)?;
+    }
     buf_writer.flush()?;
+
+    // Unless it's configured to trust the exit code, git treats any non-zero exit code from an
+    // external diff program as a failure.
+    Ok(differs && revisions.is_some())
+}
+
+/// Diff a single file from git and render the results to `writer`, returning whether the file
+/// differs.
+fn diff_git_file(
+    file_diff: &GitFileDiff,
+    file_type: Option<&str>,
+    config: &Config,
+    renderer: &Renderers,
+    brief: bool,
+    writer: &mut Term,
+    state: &mut RenderState,
+) -> Result<bool> {
+    // The header would make structured output like JSON invalid, and structured output already has
+    // the filenames
+    if !brief && !renderer.is_structured() {
+        for line in file_diff.header() {
+            writeln!(writer, "{line}")?;
+        }
+    }
+    let path = file_diff.path();
+    let language = match file_type {
+        Some(file_type) => Some(file_type),
+        None => path
+            .extension()
+            .and_then(|ext| lang_name_from_file_ext(&ext.to_string_lossy(), &config.grammar).ok()),
+    };
+    let supported =
+        language.is_some_and(|language| generate_language(language, &config.grammar).is_ok());
+
+    let old_name = file_diff.old.display_name("a/");
+    let new_name = file_diff.new.display_name("b/");
+
+    match (supported, language) {
+        (true, Some(language)) => match (file_diff.old.text(), file_diff.new.text()) {
+            (Ok(old_text), Ok(new_text)) => {
+                let source = |name: &str, text: &str| Source::Text {
+                    name: name.into(),
+                    text: text.into(),
+                };
+                // The documents are named by their paths in the repository, without git's `a/`
+                // and `b/` prefixes, so renderers that refer to files (like patches and quickfix
+                // lists) point at files that exist
+                let session = Differ::new(config).with_language(language).diff(
+                    source(&file_diff.old.display_name(""), old_text),
+                    source(&file_diff.new.display_name(""), new_text),
+                )?;
+                if brief {
+                    let differs = session.has_changes()?;
+                    if differs {
+                        writeln!(writer, "Files {old_name} and {new_name} differ")?;
+                    }
+                    return Ok(differs);
+                }
+                let term_info = writer.clone();
+                return Ok(session.render_part(renderer, writer, Some(&term_info), state)?);
+            }
+            // Files that aren't text can't be parsed, so they're handled like unsupported files
+            (Err(e), _) | (_, Err(e)) => info!("{e}"),
+        },
+        _ => info!("{} is not supported", path.display()),
+    }
+
+    match (
+        &file_diff.old.file,
+        &file_diff.new.file,
+        &config.fallback_cmd,
+    ) {
+        // The fallback would display the diff, which we don't want in brief mode or in the middle
+        // of structured output
+        (Some(old), Some(new), Some(cmd)) if !brief && !renderer.is_structured() => {
+            // The fallback writes directly to stdout, so we need to flush what we have so far to
+            // keep the output in order.
+            writer.flush()?;
+            diff_fallback(cmd, old, new)
+        }
+        _ => {
+            write_message(
+                writer,
+                renderer,
+                brief,
+                &format!("Files {old_name} and {new_name} differ"),
+            )?;
+            Ok(true)
+        }
+    }
+}
+
+/// Write a message about files that weren't rendered, like `Files a and b differ`.
+///
+/// Structured output like SARIF has to stay a single valid document, so the message is written to
+/// stderr instead, unless we're in brief mode where nothing is rendered.
+fn write_message(
+    writer: &mut Term,
+    renderer: &Renderers,
+    brief: bool,
+    message: &str,
+) -> Result<()> {
+    if renderer.is_structured() && !brief {
+        eprintln!("{message}");
+    } else {
+        writeln!(writer, "{message}")?;
+    }
     Ok(())
 }
 
@@ -147,10 +504,19 @@ fn dump_default_config() -> Result<()> {
     Ok(())
 }
 
-/// Run the diff fallback command using the command and the given paths.
-fn diff_fallback(cmd: &str, old: &Path, new: &Path) -> io::Result<Child> {
+/// Run the diff fallback command using the command and the given paths, returning whether the
+/// files differ.
+///
+/// This waits for the command to finish so its output isn't interleaved with ours. The command is
+/// expected to use the same exit codes as `diff`.
+fn diff_fallback(cmd: &str, old: &Path, new: &Path) -> Result<bool> {
     debug!("Spawning diff fallback process");
-    Command::new(cmd).args([old, new]).spawn()
+    let status = Command::new(cmd).args([old, new]).status()?;
+    match status.code() {
+        Some(0) => Ok(false),
+        Some(code) if code == i32::from(EXIT_DIFFERENCES) => Ok(true),
+        _ => anyhow::bail!("The diff fallback command failed ({status})"),
+    }
 }
 
 /// Print a list of the languages that this instance of diffsitter was compiled with
@@ -177,11 +543,8 @@ fn print_shell_completion(shell: clap_complete::Shell) {
     clap_complete::generate(shell, &mut app, APP_NAME, &mut io::stdout());
 }
 
-fn main() -> Result<()> {
-    // Set up a panic handler that will yield more human-readable errors.
-    #[cfg(panic = "unwind")]
-    setup_panic!();
-
+/// Run the command the user asked for, returning whether the inputs differ
+fn run() -> Result<bool> {
     #[cfg(feature = "better-build-info")]
     shadow!(build);
 
@@ -200,37 +563,80 @@ fn main() -> Result<()> {
     let config = derive_config(&args)?;
 
     // Users can supply a command that will *not* run a diff, which we handle here
-    if let Some(cmd) = args.cmd {
+    let log_level = if args.debug {
+        LevelFilter::Trace
+    } else {
+        LevelFilter::Off
+    };
+    pretty_env_logger::formatted_timed_builder()
+        .filter_level(log_level)
+        .init();
+    console_utils::set_term_colors(args.color_output);
+
+    if let Some(cmd) = &args.cmd {
         match cmd {
             Command::List => list_supported_languages(),
             Command::DumpDefaultConfig => dump_default_config()?,
             Command::GenCompletion { shell } => {
-                print_shell_completion(shell.into());
+                print_shell_completion((*shell).into());
             }
+            Command::Git {
+                revisions,
+                external_diff_args,
+            } => return run_git_diff(revisions.as_deref(), external_diff_args, &args, &config),
         }
+        Ok(false)
     } else {
-        let log_level = if args.debug {
-            LevelFilter::Trace
-        } else {
-            LevelFilter::Off
-        };
-        pretty_env_logger::formatted_timed_builder()
-            .filter_level(log_level)
-            .init();
-        console_utils::set_term_colors(args.color_output);
-        // First check if the input files can be parsed with tree-sitter.
-        let files_supported = are_input_files_supported(&args, &config);
-
-        // If the files are supported by our grammars, awesome. Otherwise fall back to a diff
-        // utility if one is specified.
-        if files_supported {
-            run_diff(args, config)?;
+        let stdin_path = Some(Path::new(STDIN_PATH));
+        if args.old.as_deref() == stdin_path && args.new.as_deref() == stdin_path {
+            anyhow::bail!("Only one of the inputs can be read from stdin.");
+        }
+        let is_dir_diff = matches!(
+            (&args.old, &args.new),
+            (Some(old), Some(new)) if old.is_dir() && new.is_dir()
+        );
+
+        // Directories are diffed file by file, so checking whether the files are supported (and
+        // falling back to the fallback command) is done for each file.
+        //
+        // Otherwise, first check if the input files can be parsed with tree-sitter. If the files
+        // are supported by our grammars, awesome. Otherwise fall back to a diff utility if one is
+        // specified.
+        if is_dir_diff {
+            run_dir_diff(args, config)
+        } else if are_input_files_supported(&args, &config) {
+            run_diff(args, config)
         } else if let Some(cmd) = config.fallback_cmd {
             info!("Input files are not supported but user has configured diff fallback");
-            diff_fallback(&cmd, &args.old.unwrap(), &args.new.unwrap())?;
+            let old = args.old.unwrap();
+            let new = args.new.unwrap();
+            if !args.brief {
+                return diff_fallback(&cmd, &old, &new);
+            }
+            // The fallback would display the diff, so the files are compared directly instead
+            let differs = fs::read(&old)? != fs::read(&new)?;
+            if differs {
+                println!("Files {} and {} differ", old.display(), new.display());
+            }
+            Ok(differs)
         } else {
             anyhow::bail!("Unsupported file type with no fallback command specified.");
         }
     }
-    Ok(())
+}
+
+fn main() -> ExitCode {
+    // Set up a panic handler that will yield more human-readable errors.
+    #[cfg(panic = "unwind")]
+    setup_panic!();
+
+    // Like `diff`, the exit code reports whether the inputs differ or there was an error
+    match run() {
+        Ok(false) => ExitCode::SUCCESS,
+        Ok(true) => ExitCode::from(EXIT_DIFFERENCES),
+        Err(error) => {
+            eprintln!("Error: {error:?}");
+            ExitCode::from(EXIT_ERROR)
+        }
+    }
 }
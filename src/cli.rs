@@ -1,4 +1,4 @@
-use crate::console_utils::ColorOutputPolicy;
+use crate::{console_utils::ColorOutputPolicy, diff::EngineKind};
 use clap::Parser;
 use std::path::PathBuf;
 use strum_macros::EnumString;
@@ -12,8 +12,8 @@ pub struct Args {
     /// should contain debug logging info.
     #[clap(short, long)]
     pub debug: bool,
-    /// Run a subcommand that doesn't perform a diff. Valid options are: "list",
-    /// "dump_default_config", and "build_info".
+    /// Run a subcommand. Valid options are: "list", "dump_default_config", "build_info", and
+    /// "git".
     ///
     /// * "list" lists all of the filetypes/languages that this program was compiled with support
     ///   for
@@ -21,24 +21,30 @@ pub struct Args {
     /// * "dump_default_config" will dump the default configuration to stdout
     ///
     /// * "build_info" prints extended build information
+    ///
+    /// * "git" diffs files from git, either as an external diff program or between two revisions
     #[clap(subcommand)]
     pub cmd: Option<Command>,
     /// The first file to compare against
     ///
-    /// Text that is in this file but is not in the new file is considered a deletion
+    /// Text that is in this file but is not in the new file is considered a deletion. Use `-` to
+    /// read from stdin.
     // #[clap(name = "OLD", parse(from_os_str), required_unless_present = "cmd")]
     #[clap(name = "OLD")]
     pub old: Option<PathBuf>,
     /// The file that the old file is compared against
     ///
-    /// Text that is in this file but is not in the old file is considered an addition
+    /// Text that is in this file but is not in the old file is considered an addition. Use `-` to
+    /// read from stdin.
     // #[clap(name = "NEW", parse(from_os_str), required_unless_present = "cmd")]
     #[clap(name = "NEW")]
     pub new: Option<PathBuf>,
     /// Manually set the file type for the given files
     ///
     /// This will dictate which parser is used with the difftool. You can list all of the valid
-    /// file type strings with `diffsitter --cmd list`
+    /// file type strings with `diffsitter --cmd list`. An input without a file extension, like
+    /// stdin or process substitution, uses the file type of the other input, so this is required
+    /// if neither input has a file extension.
     #[clap(short = 't', long)]
     pub file_type: Option<String>,
     /// Use the config provided at the given path
@@ -64,9 +70,50 @@ pub struct Args {
 
     /// Specify which renderer tag to use.
     ///
-    /// If no option is supplied then this will fall back to the default renderer.
+    /// This can be the name of a renderer or the tag of a custom renderer from the config. If no
+    /// option is supplied then this will fall back to the default renderer.
     #[clap(short, long)]
     pub renderer: Option<String>,
+
+    /// The number of unchanged lines to display around each hunk.
+    ///
+    /// This overrides the `context-lines` setting of the unified and patch renderers from the
+    /// config.
+    #[clap(short = 'U', long = "context")]
+    pub context_lines: Option<usize>,
+
+    /// Display line numbers in a gutter before each line.
+    ///
+    /// This overrides the `line-numbers` setting of the unified renderer from the config.
+    #[clap(long)]
+    pub line_numbers: bool,
+
+    /// The diff engine to use. Valid values are: "myers", "patience", "histogram", and "tree".
+    ///
+    /// This overrides the `engine` setting in the `diff` section of the config.
+    #[clap(long)]
+    pub engine: Option<EngineKind>,
+
+    /// Report blocks of code that were moved as moves, rather than as deletions and additions.
+    ///
+    /// This overrides the `detect-moves` setting in the `diff` section of the config.
+    #[clap(long)]
+    pub detect_moves: bool,
+
+    /// Ignore changes to comments and docstrings.
+    ///
+    /// This overrides the `ignore-comments` setting in the `input-processing` section of the
+    /// config.
+    #[clap(long)]
+    pub ignore_comments: bool,
+
+    /// Only report whether the files differ, without displaying the diff.
+    ///
+    /// Like `diff --brief`, this prints a line for each pair of files that differ. The exit code
+    /// is 0 if the files don't have any semantic differences, 1 if they do, and 2 if there was an
+    /// error, regardless of this flag.
+    #[clap(short = 'q', long, visible_alias = "quiet")]
+    pub brief: bool,
 }
 
 /// A wrapper struct for `clap_complete::Shell`.
@@ -106,7 +153,7 @@ impl From<ShellWrapper> for clap_complete::Shell {
 }
 
 /// Commands related to the configuration
-#[derive(Debug, Eq, PartialEq, Clone, Copy, Parser, EnumString)]
+#[derive(Debug, Eq, PartialEq, Clone, Parser, EnumString)]
 #[strum(serialize_all = "snake_case")]
 pub enum Command {
     /// List the languages that this program was compiled for
@@ -122,4 +169,27 @@ pub enum Command {
         /// This will print the shell completion script to stdout. bash, zsh, and fish are supported.
         shell: ShellWrapper,
     },
+
+    /// Diff files from git
+    ///
+    /// This can be used as an external diff program for git, e.g. by setting
+    /// `GIT_EXTERNAL_DIFF="diffsitter git"`, in which case git supplies the arguments. It can also
+    /// diff two revisions of the repository in the current directory with `--revisions`.
+    Git {
+        /// Diff all of the files that changed between two revisions
+        #[clap(
+            long,
+            num_args = 2,
+            value_names = ["OLD_REV", "NEW_REV"],
+            conflicts_with = "external_diff_args"
+        )]
+        revisions: Option<Vec<String>>,
+
+        /// The arguments git passes to an external diff program
+        ///
+        /// These are: `path old-file old-hex old-mode new-file new-hex new-mode`, with
+        /// `new-path rename-info` appended for renames and copies.
+        #[clap(num_args = 7..=9, required_unless_present = "revisions")]
+        external_diff_args: Vec<String>,
+    },
 }
@@ -0,0 +1,129 @@
+//! Utilities for finding the comments and docstrings in a syntax tree.
+//!
+//! Every grammar names its comment nodes differently, so we keep track of the comment node kinds
+//! for each of the grammars that diffsitter is compiled with. Users can add the comment kinds for
+//! other grammars, like dynamically loaded grammars, in the config.
+
+use phf::phf_map;
+use std::collections::HashSet;
+use tree_sitter::Node as TSNode;
+
+/// A mapping of languages to the kinds of their comment nodes
+///
+/// This covers every grammar that's compiled in `build.rs`. Markdown doesn't have comment nodes,
+/// since its HTML comments are parsed as `html_block` nodes along with any other HTML, so they
+/// can't be ignored without ignoring the rest of the HTML.
+static COMMENT_KINDS: phf::Map<&'static str, &'static [&'static str]> = phf_map! {
+    "bash" => &["comment"],
+    "c" => &["comment"],
+    "c_sharp" => &["comment"],
+    "cpp" => &["comment"],
+    "css" => &["comment"],
+    "go" => &["comment"],
+    "hcl" => &["comment"],
+    "java" => &["line_comment", "block_comment"],
+    "json" => &["comment"],
+    "markdown" => &[],
+    "ocaml" => &["comment"],
+    "php" => &["comment"],
+    "python" => &["comment"],
+    "ruby" => &["comment"],
+    "rust" => &["line_comment", "block_comment"],
+    "tsx" => &["comment", "html_comment"],
+    "typescript" => &["comment", "html_comment"],
+};
+
+/// The languages that use string literals as docstrings
+static DOCSTRING_LANGUAGES: &[&str] = &["python"];
+
+/// Finds the comments and docstrings in a syntax tree for a particular language
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommentFilter<'a> {
+    /// The kinds of comment nodes
+    kinds: HashSet<&'a str>,
+    /// Whether the language has docstrings
+    docstrings: bool,
+}
+
+impl<'a> CommentFilter<'a> {
+    /// Create a filter for a language.
+    ///
+    /// `extra_kinds` are comment kinds in addition to the ones we know about for the language,
+    /// which is useful for grammars that diffsitter wasn't compiled with.
+    #[must_use]
+    pub fn new(language: &str, extra_kinds: &'a [String]) -> Self {
+        let builtin_kinds = COMMENT_KINDS.get(language).copied().unwrap_or_default();
+        CommentFilter {
+            kinds: builtin_kinds
+                .iter()
+                .copied()
+                .chain(extra_kinds.iter().map(String::as_str))
+                .collect(),
+            docstrings: DOCSTRING_LANGUAGES.contains(&language),
+        }
+    }
+
+    /// Whether a node is part of a comment or a docstring.
+    ///
+    /// Comments can have children, like the delimiters of a block comment, so this checks every
+    /// ancestor of the node.
+    #[must_use]
+    pub fn is_comment(&self, node: TSNode) -> bool {
+        std::iter::successors(Some(node), TSNode::parent).any(|ancestor| {
+            self.kinds.contains(ancestor.kind()) || (self.docstrings && is_docstring(ancestor))
+        })
+    }
+}
+
+/// Whether a node is a docstring, which is a statement that only has a string literal and is the
+/// first statement of a module, class or function.
+fn is_docstring(node: TSNode) -> bool {
+    if node.kind() != "expression_statement" || node.named_child_count() != 1 {
+        return false;
+    }
+    let is_string = node
+        .named_child(0)
+        .is_some_and(|child| matches!(child.kind(), "string" | "concatenated_string"));
+    let Some(parent) = node.parent() else {
+        return false;
+    };
+    let is_first_statement = first_statement(parent) == Some(node);
+    let is_in_definition = match parent.kind() {
+        "module" => true,
+        "block" => parent.parent().is_some_and(|definition| {
+            matches!(
+                definition.kind(),
+                "function_definition" | "class_definition"
+            )
+        }),
+        _ => false,
+    };
+    is_string && is_first_statement && is_in_definition
+}
+
+/// Get the first named child of a node that isn't a comment.
+fn first_statement(node: TSNode) -> Option<TSNode> {
+    let mut cursor = node.walk();
+    let first = node
+        .named_children(&mut cursor)
+        .find(|child| child.kind() != "comment");
+    first
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("rust", &[], &["line_comment", "block_comment"])]
+    #[test_case("python", &[], &["comment"])]
+    #[test_case("markdown", &[], &[])]
+    #[test_case("haskell", &[], &[])]
+    #[test_case("haskell", &["comment"], &["comment"])]
+    #[test_case("go", &["extra_comment"], &["comment", "extra_comment"])]
+    fn test_comment_kinds(language: &str, extra_kinds: &[&str], expected: &[&str]) {
+        let extra_kinds: Vec<String> = extra_kinds.iter().map(ToString::to_string).collect();
+        let filter = CommentFilter::new(language, &extra_kinds);
+        assert_eq!(filter.kinds, expected.iter().copied().collect());
+    }
+}
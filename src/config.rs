@@ -1,8 +1,8 @@
 //! Utilities and definitions for config handling
 
 use crate::{
-    cli::Args, figment_utils::JsonProvider, input_processing::TreeSitterProcessor,
-    parse::GrammarConfig, render::RenderConfig,
+    cli::Args, diff::DiffConfig, figment_utils::JsonProvider,
+    input_processing::TreeSitterProcessor, parse::GrammarConfig, render::RenderConfig,
 };
 use anyhow::Result;
 use figment::{
@@ -48,6 +48,9 @@ pub struct Config {
     /// Options for processing tree-sitter input.
     pub input_processing: TreeSitterProcessor,
 
+    /// Options for computing diffs
+    pub diff: DiffConfig,
+
     /// The program to invoke if the given files can not be parsed by the available tree-sitter
     /// parsers.
     ///
@@ -97,6 +100,8 @@ impl Config {
             fig
         };
         let config: Config = fig.extract()?;
+        config.formatting.validate()?;
+        config.input_processing.validate(&config.grammar)?;
         Ok(config)
     }
 
@@ -1,14 +1,18 @@
 //! Structs and other convenience methods for handling logical concepts pertaining to diffs, such
 //! as hunks.
 
+use crate::diff_result::Position;
 use crate::input_processing::{EditType, Entry};
 use crate::neg_idx_vec::NegIdxVec;
-use anyhow::Result;
+use crate::tree_diff::TreeDiff;
 use logging_timer::time;
-use serde::Serialize;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::hash::Hash;
 use std::iter::FromIterator;
 use std::ops::Range;
+use strum::{Display, EnumString};
 use thiserror::Error;
 
 /// Find the length of the common prefix between the ranges specified for `a` and `b`.
@@ -155,6 +159,11 @@ impl<'a> Hunk<'a> {
         self.0.last().map(|x| x.line_index)
     }
 
+    /// Returns an iterator over every entry in the hunk, in order
+    pub fn entries(&self) -> impl Iterator<Item = &'a Entry<'a>> + '_ {
+        self.0.iter().flat_map(|line| line.entries.iter().copied())
+    }
+
     /// Returns whether an entry can be pushed onto the current hunk.
     ///
     /// This method is exposed so users can check if the push back operation would fail. This is
@@ -234,7 +243,7 @@ impl<'a> Default for Hunk<'a> {
 ///
 /// A lot of items in the diff are delineated by whether they come from the old document or the new
 /// one. This enum generically defines an enum wrapper over those document types.
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub enum DocumentType<T: Debug + PartialEq + Serialize + Clone> {
     Old(T),
     New(T),
@@ -374,7 +383,10 @@ impl<'a> RichHunksBuilder<'a> {
     }
 
     /// Add an entry to the hunks.
-    pub fn push_back(&mut self, entry_wrapper: DocumentType<&'a Entry<'a>>) -> Result<()> {
+    pub fn push_back(
+        &mut self,
+        entry_wrapper: DocumentType<&'a Entry<'a>>,
+    ) -> Result<(), HunkInsertionError> {
         let insertion_idx = self.get_hunk_for_insertion(&entry_wrapper)?;
         self.hunks.0[insertion_idx]
             .as_mut()
@@ -395,7 +407,7 @@ impl<'a> Hunks<'a> {
         Hunks(Vec::new())
     }
 
-    pub fn push_back(&mut self, entry: &'a Entry<'a>) -> Result<()> {
+    pub fn push_back(&mut self, entry: &'a Entry<'a>) -> Result<(), HunkInsertionError> {
         if let Some(hunk) = self.0.last_mut() {
             match hunk.can_push_back(entry) {
                 Ok(()) => hunk.push_back(entry),
@@ -753,8 +765,282 @@ impl Myers {
     }
 }
 
+/// Emit the edits for a range where one of the inputs is empty.
+///
+/// This returns `false` if neither range is empty, in which case nothing is emitted.
+fn diff_trivial_range<'elem, T>(
+    res: &mut Vec<EditType<&'elem T>>,
+    old: &'elem [T],
+    old_range: &Range<usize>,
+    new: &'elem [T],
+    new_range: &Range<usize>,
+) -> bool {
+    if old_range.is_empty() {
+        res.extend(new[new_range.clone()].iter().map(EditType::Addition));
+        true
+    } else if new_range.is_empty() {
+        res.extend(old[old_range.clone()].iter().map(EditType::Deletion));
+        true
+    } else {
+        false
+    }
+}
+
+/// Strip the common prefix and suffix from a pair of ranges.
+fn trim_common_affixes<T: PartialEq>(
+    old: &[T],
+    old_range: &mut Range<usize>,
+    new: &[T],
+    new_range: &mut Range<usize>,
+) {
+    let common_pref_len = common_prefix_len(old, old_range.clone(), new, new_range.clone());
+    old_range.start += common_pref_len;
+    new_range.start += common_pref_len;
+
+    let common_suf_len = common_suffix_len(old, old_range.clone(), new, new_range.clone());
+    old_range.end = old_range.start.max(old_range.end - common_suf_len);
+    new_range.end = new_range.start.max(new_range.end - common_suf_len);
+}
+
+/// The patience diff algorithm
+///
+/// Patience diff only aligns the inputs on elements that occur exactly once in each input, which
+/// tend to be meaningful (like a function name) rather than noise (like a closing brace). It
+/// recursively diffs the ranges between those elements, and falls back to [Myers] for ranges that
+/// don't have any unique elements in common.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct Patience {}
+
+impl<'elem, T> Engine<'elem, T> for Patience
+where
+    T: Eq + Hash + 'elem + std::fmt::Debug,
+{
+    type Container = Vec<EditType<&'elem T>>;
+
+    fn diff(&self, a: &'elem [T], b: &'elem [T]) -> Self::Container {
+        let mut res = Vec::with_capacity(a.len() + b.len());
+        let mut frontiers = MyersFrontiers::new(a.len(), b.len());
+        Patience::diff_impl(&mut res, a, 0..a.len(), b, 0..b.len(), &mut frontiers);
+        res
+    }
+}
+
+impl Patience {
+    fn diff_impl<'elem, T: Eq + Hash + Debug + 'elem>(
+        res: &mut Vec<EditType<&'elem T>>,
+        old: &'elem [T],
+        mut old_range: Range<usize>,
+        new: &'elem [T],
+        mut new_range: Range<usize>,
+        frontiers: &mut MyersFrontiers,
+    ) {
+        trim_common_affixes(old, &mut old_range, new, &mut new_range);
+        if diff_trivial_range(res, old, &old_range, new, &new_range) {
+            return;
+        }
+
+        let anchors = Patience::unique_anchors(old, old_range.clone(), new, new_range.clone());
+        if anchors.is_empty() {
+            Myers::diff_impl(res, old, old_range, new, new_range, frontiers);
+            return;
+        }
+
+        let (mut old_start, mut new_start) = (old_range.start, new_range.start);
+        for (old_idx, new_idx) in anchors {
+            Patience::diff_impl(
+                res,
+                old,
+                old_start..old_idx,
+                new,
+                new_start..new_idx,
+                frontiers,
+            );
+            old_start = old_idx + 1;
+            new_start = new_idx + 1;
+        }
+        Patience::diff_impl(
+            res,
+            old,
+            old_start..old_range.end,
+            new,
+            new_start..new_range.end,
+            frontiers,
+        );
+    }
+
+    /// Find the elements that occur exactly once in both ranges, keeping the longest sequence of
+    /// them that's in the same order in both ranges.
+    ///
+    /// This returns the indices of the elements in the old and new inputs.
+    fn unique_anchors<T: Eq + Hash>(
+        old: &[T],
+        old_range: Range<usize>,
+        new: &[T],
+        new_range: Range<usize>,
+    ) -> Vec<(usize, usize)> {
+        // The number of occurrences and the last index of each element in the old and new ranges
+        let mut occurrences: HashMap<&T, (usize, usize, usize, usize)> = HashMap::new();
+        for idx in old_range {
+            let entry = occurrences.entry(&old[idx]).or_insert((0, 0, 0, 0));
+            entry.0 += 1;
+            entry.1 = idx;
+        }
+        for idx in new_range {
+            if let Some(entry) = occurrences.get_mut(&new[idx]) {
+                entry.2 += 1;
+                entry.3 = idx;
+            }
+        }
+        let mut pairs: Vec<(usize, usize)> = occurrences
+            .into_values()
+            .filter(|&(old_count, _, new_count, _)| old_count == 1 && new_count == 1)
+            .map(|(_, old_idx, _, new_idx)| (old_idx, new_idx))
+            .collect();
+        pairs.sort_unstable();
+        longest_increasing_pairs(&pairs)
+    }
+}
+
+/// The histogram diff algorithm
+///
+/// This is an extension of [patience diff](Patience) that was popularized by git. Rather than
+/// requiring elements to be unique, it aligns the inputs on the longest common run that contains
+/// the least frequent element, then recursively diffs the ranges before and after that run. It
+/// falls back to [Myers] for ranges without any common elements, or where every common element is
+/// too frequent to be a useful anchor.
+#[derive(Eq, PartialEq, Copy, Clone, Debug, Default)]
+pub struct Histogram {}
+
+impl<'elem, T> Engine<'elem, T> for Histogram
+where
+    T: Eq + Hash + 'elem + std::fmt::Debug,
+{
+    type Container = Vec<EditType<&'elem T>>;
+
+    fn diff(&self, a: &'elem [T], b: &'elem [T]) -> Self::Container {
+        let mut res = Vec::with_capacity(a.len() + b.len());
+        let mut frontiers = MyersFrontiers::new(a.len(), b.len());
+        Histogram::diff_impl(&mut res, a, 0..a.len(), b, 0..b.len(), &mut frontiers);
+        res
+    }
+}
+
+/// A common run of elements between two inputs
+#[derive(Debug, PartialEq, Eq, Clone, Copy)]
+struct CommonRegion {
+    /// The index of the start of the run in each input
+    start: Coordinates<usize>,
+    /// The length of the run
+    len: usize,
+    /// The number of occurrences in the old range of the least frequent element in the run
+    occurrences: usize,
+}
+
+impl Histogram {
+    /// Elements that occur more often than this in the old range aren't used as anchors, which
+    /// bounds the amount of work done for very repetitive inputs.
+    const MAX_OCCURRENCES: usize = 64;
+
+    fn diff_impl<'elem, T: Eq + Hash + Debug + 'elem>(
+        res: &mut Vec<EditType<&'elem T>>,
+        old: &'elem [T],
+        mut old_range: Range<usize>,
+        new: &'elem [T],
+        mut new_range: Range<usize>,
+        frontiers: &mut MyersFrontiers,
+    ) {
+        trim_common_affixes(old, &mut old_range, new, &mut new_range);
+        if diff_trivial_range(res, old, &old_range, new, &new_range) {
+            return;
+        }
+
+        let Some(region) = Histogram::find_region(old, old_range.clone(), new, new_range.clone())
+        else {
+            Myers::diff_impl(res, old, old_range, new, new_range, frontiers);
+            return;
+        };
+        let Coordinates {
+            old: old_start,
+            new: new_start,
+        } = region.start;
+        Histogram::diff_impl(
+            res,
+            old,
+            old_range.start..old_start,
+            new,
+            new_range.start..new_start,
+            frontiers,
+        );
+        Histogram::diff_impl(
+            res,
+            old,
+            old_start + region.len..old_range.end,
+            new,
+            new_start + region.len..new_range.end,
+            frontiers,
+        );
+    }
+
+    /// Find the common run with the least frequent elements in the old range, preferring longer
+    /// runs when there's a tie.
+    fn find_region<T: Eq + Hash>(
+        old: &[T],
+        old_range: Range<usize>,
+        new: &[T],
+        new_range: Range<usize>,
+    ) -> Option<CommonRegion> {
+        let mut positions: HashMap<&T, Vec<usize>> = HashMap::new();
+        for idx in old_range.clone() {
+            positions.entry(&old[idx]).or_default().push(idx);
+        }
+
+        let mut best: Option<CommonRegion> = None;
+        let mut new_idx = new_range.start;
+        while new_idx < new_range.end {
+            let mut next_idx = new_idx + 1;
+            let old_positions = positions
+                .get(&new[new_idx])
+                .filter(|positions| positions.len() <= Self::MAX_OCCURRENCES);
+
+            for &old_idx in old_positions.into_iter().flatten() {
+                // Extend the run in both directions from the common element
+                let before =
+                    common_suffix_len(old, old_range.start..old_idx, new, new_range.start..new_idx);
+                let after =
+                    common_prefix_len(old, old_idx..old_range.end, new, new_idx..new_range.end);
+                let start = old_idx - before;
+                let len = before + after;
+                let occurrences = old[start..start + len]
+                    .iter()
+                    .map(|elem| positions[elem].len())
+                    .min()
+                    .unwrap_or_default();
+                let region = CommonRegion {
+                    start: Coordinates {
+                        old: start,
+                        new: new_idx - before,
+                    },
+                    len,
+                    occurrences,
+                };
+                let is_better = best.is_none_or(|best| {
+                    region.occurrences < best.occurrences
+                        || (region.occurrences == best.occurrences && region.len > best.len)
+                });
+                if is_better {
+                    best = Some(region);
+                }
+                // There's no point in searching for runs that start inside of this one
+                next_idx = next_idx.max(new_idx + after);
+            }
+            new_idx = next_idx;
+        }
+        best
+    }
+}
+
 impl<'a> TryFrom<Vec<EditType<&'a Entry<'a>>>> for RichHunks<'a> {
-    type Error = anyhow::Error;
+    type Error = HunkInsertionError;
 
     fn try_from(edits: Vec<EditType<&'a Entry<'a>>>) -> Result<Self, Self::Error> {
         let mut builder = RichHunksBuilder::new();
@@ -770,19 +1056,283 @@ impl<'a> TryFrom<Vec<EditType<&'a Entry<'a>>>> for RichHunks<'a> {
     }
 }
 
+/// Options for computing diffs
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct DiffConfig {
+    /// The diff engine to use
+    pub engine: EngineKind,
+
+    /// Whether to detect blocks of code that were moved
+    ///
+    /// This is disabled by default, so a block of code that was moved shows up as a deletion and
+    /// an unrelated addition.
+    pub detect_moves: bool,
+
+    /// The minimum number of entries a block of code needs to have to be considered a move
+    ///
+    /// Short runs of entries like a closing brace are frequently identical by coincidence, so
+    /// reporting them as moves would be noisy.
+    pub min_move_entries: usize,
+}
+
+impl Default for DiffConfig {
+    fn default() -> Self {
+        Self {
+            engine: EngineKind::default(),
+            detect_moves: false,
+            min_move_entries: 16,
+        }
+    }
+}
+
+/// The diff engines that can be selected in the config
+#[derive(
+    Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default, Display, EnumString,
+)]
+#[serde(rename_all = "snake_case")]
+#[strum(serialize_all = "snake_case")]
+pub enum EngineKind {
+    /// Diff the leaves of the syntax trees with [Myers' algorithm](Myers)
+    #[default]
+    Myers,
+    /// Align the leaves of the syntax trees on unique elements with [patience diff](Patience)
+    Patience,
+    /// Align the leaves of the syntax trees on rare elements with [histogram diff](Histogram)
+    Histogram,
+    /// Match the subtrees of the syntax trees with the [tree engine](TreeDiff)
+    Tree,
+}
+
+/// A block of code that was moved from one place to another
+///
+/// A move pairs a run of deleted entries from a hunk in the old document with an identical run of
+/// added entries from a hunk in the new document.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Move {
+    /// The index of the hunk in the old document in the diff's hunks
+    pub old_hunk: usize,
+    /// The index of the hunk in the new document in the diff's hunks
+    pub new_hunk: usize,
+    /// Where the block was in the old document
+    pub source: Range<Position>,
+    /// Where the block is in the new document
+    pub destination: Range<Position>,
+}
+
+impl Move {
+    /// Create a move between two non-empty runs of entries.
+    fn new(old_hunk: usize, old: &[&Entry], new_hunk: usize, new: &[&Entry]) -> Self {
+        let span = |entries: &[&Entry]| {
+            entries[0].start_position().into()..entries[entries.len() - 1].end_position().into()
+        };
+        Move {
+            old_hunk,
+            new_hunk,
+            source: span(old),
+            destination: span(new),
+        }
+    }
+}
+
+/// Find the blocks of code that were moved rather than deleted and added.
+///
+/// This looks for runs of at least `min_entries` entries that were deleted from a hunk in the old
+/// document and added, unchanged, to a hunk in the new document. Every added entry is part of at
+/// most one move. Runs are matched greedily, preferring the longest run that starts at a given
+/// deleted entry.
+#[time("info", "diff::{}")]
+#[must_use]
+pub fn find_moves(hunks: &RichHunks, min_entries: usize) -> Vec<Move> {
+    let window_len = min_entries.max(1);
+
+    // The entries of each hunk, and whether each entry in a new hunk was already matched
+    let entries: Vec<Vec<&Entry>> = hunks
+        .0
+        .iter()
+        .map(|hunk| hunk.as_ref().entries().collect())
+        .collect();
+    let mut matched: Vec<Vec<bool>> = entries.iter().map(|e| vec![false; e.len()]).collect();
+
+    // Index every window of added entries so we can look up where a run of deleted entries was
+    // added without comparing every pair of hunks
+    let mut windows: HashMap<&[&Entry], Vec<(usize, usize)>> = HashMap::new();
+    for (hunk_idx, hunk) in hunks.0.iter().enumerate() {
+        if let DocumentType::New(_) = hunk {
+            for (offset, window) in entries[hunk_idx].windows(window_len).enumerate() {
+                windows.entry(window).or_default().push((hunk_idx, offset));
+            }
+        }
+    }
+
+    let mut moves = Vec::new();
+    for (old_idx, hunk) in hunks.0.iter().enumerate() {
+        if let DocumentType::New(_) = hunk {
+            continue;
+        }
+        let old = &entries[old_idx];
+        let mut start = 0;
+
+        while start + window_len <= old.len() {
+            let candidates = windows
+                .get(&old[start..start + window_len])
+                .map_or(&[][..], Vec::as_slice);
+            // Find the longest run of unmatched entries starting at this entry
+            let best = candidates
+                .iter()
+                .map(|&(new_idx, offset)| {
+                    let len = old[start..]
+                        .iter()
+                        .zip(&entries[new_idx][offset..])
+                        .zip(&matched[new_idx][offset..])
+                        .take_while(|((a, b), &is_matched)| a == b && !is_matched)
+                        .count();
+                    (len, new_idx, offset)
+                })
+                .filter(|&(len, _, _)| len >= window_len)
+                .max_by_key(|&(len, _, _)| len);
+
+            if let Some((len, new_idx, offset)) = best {
+                matched[new_idx][offset..offset + len].fill(true);
+                moves.push(Move::new(
+                    old_idx,
+                    &old[start..start + len],
+                    new_idx,
+                    &entries[new_idx][offset..offset + len],
+                ));
+                start += len;
+            } else {
+                start += 1;
+            }
+        }
+    }
+    moves
+}
+
+/// Group the hunks that overlap in the edit script, so the deletions and additions that make up a
+/// change can be displayed together.
+///
+/// The unchanged entries split the edit script into gaps, and every edit falls into one of these
+/// gaps. A hunk spans the gaps from its first entry to its last entry, and hunks whose spans
+/// overlap are grouped. `old` and `new` are the entries that were diffed to produce the hunks.
+///
+/// Each group lists the indices of its old hunks followed by the indices of its new hunks, so a
+/// change reads as "before then after". The groups are in the order they appear in the edit
+/// script.
+#[time("info", "diff::{}")]
+#[must_use]
+pub fn group_hunks(hunks: &RichHunks, old: &[Entry], new: &[Entry]) -> Vec<Vec<usize>> {
+    let is_new = |hunk_idx: usize| matches!(hunks.0[hunk_idx], DocumentType::New(_));
+
+    // The first and last gap that each hunk spans
+    let mut spans: Vec<Option<(usize, usize)>> = vec![None; hunks.0.len()];
+    for (document_is_new, entries) in [(false, old), (true, new)] {
+        let mut edited = (0..hunks.0.len())
+            .filter(|&hunk_idx| is_new(hunk_idx) == document_is_new)
+            .flat_map(|hunk_idx| {
+                hunks.0[hunk_idx]
+                    .as_ref()
+                    .entries()
+                    .map(move |entry| (hunk_idx, entry))
+            })
+            .peekable();
+        // The number of unchanged entries so far, which is the gap the next edit falls into
+        let mut gap = 0;
+
+        for entry in entries {
+            match edited.peek() {
+                Some(&(hunk_idx, edited_entry)) if std::ptr::eq(edited_entry, entry) => {
+                    let span = spans[hunk_idx].get_or_insert((gap, gap));
+                    span.1 = gap;
+                    edited.next();
+                }
+                _ => gap += 1,
+            }
+        }
+    }
+
+    let mut order: Vec<usize> = (0..hunks.0.len()).collect();
+    order.sort_by_key(|&hunk_idx| (spans[hunk_idx].unwrap_or_default().0, is_new(hunk_idx)));
+
+    // The groups, with the last gap that any hunk in the group spans
+    let mut groups: Vec<(usize, Vec<usize>)> = Vec::new();
+    for hunk_idx in order {
+        let (first_gap, last_gap) = spans[hunk_idx].unwrap_or_default();
+        match groups.last_mut() {
+            Some((group_last_gap, group)) if first_gap <= *group_last_gap => {
+                *group_last_gap = (*group_last_gap).max(last_gap);
+                group.push(hunk_idx);
+            }
+            _ => groups.push((last_gap, vec![hunk_idx])),
+        }
+    }
+    groups
+        .into_iter()
+        .map(|(_, mut group)| {
+            group.sort_by_key(|&hunk_idx| (is_new(hunk_idx), hunk_idx));
+            group
+        })
+        .collect()
+}
+
+/// Find the longest subsequence of pairs where the second elements are increasing.
+///
+/// The pairs are expected to be sorted by their first element, and the second elements are
+/// expected to be unique.
+#[must_use]
+pub fn longest_increasing_pairs(pairs: &[(usize, usize)]) -> Vec<(usize, usize)> {
+    // tails[k] is the index of the pair that ends the smallest increasing subsequence of length
+    // k + 1 found so far
+    let mut tails: Vec<usize> = Vec::new();
+    let mut predecessors: Vec<Option<usize>> = vec![None; pairs.len()];
+
+    for (idx, &(_, value)) in pairs.iter().enumerate() {
+        let len = tails.partition_point(|&tail| pairs[tail].1 < value);
+        predecessors[idx] = len.checked_sub(1).map(|prev| tails[prev]);
+        if len == tails.len() {
+            tails.push(idx);
+        } else {
+            tails[len] = idx;
+        }
+    }
+
+    let mut subsequence = Vec::with_capacity(tails.len());
+    let mut current = tails.last().copied();
+    while let Some(idx) = current {
+        subsequence.push(pairs[idx]);
+        current = predecessors[idx];
+    }
+    subsequence.reverse();
+    subsequence
+}
+
 /// Compute the hunks corresponding to the minimum edit path between two documents.
 ///
 /// This will process the the AST vectors with the user-provided settings.
 ///
 /// This will return two groups of [hunks](diff::Hunks) in a tuple of the form
 /// `(old_hunks, new_hunks)`.
-#[time("info", "diff::{}")]
 pub fn compute_edit_script<'a>(
     old: &'a [Entry<'a>],
     new: &'a [Entry<'a>],
-) -> Result<RichHunks<'a>> {
-    let myers = Myers::default();
-    let edit_script = myers.diff(old, new);
+) -> Result<RichHunks<'a>, HunkInsertionError> {
+    compute_edit_script_with_engine(EngineKind::default(), old, new)
+}
+
+/// Compute the hunks corresponding to the edit path between two documents with a specific diff
+/// engine.
+#[time("info", "diff::{}")]
+pub fn compute_edit_script_with_engine<'a>(
+    engine: EngineKind,
+    old: &'a [Entry<'a>],
+    new: &'a [Entry<'a>],
+) -> Result<RichHunks<'a>, HunkInsertionError> {
+    let edit_script = match engine {
+        EngineKind::Myers => Myers::default().diff(old, new),
+        EngineKind::Patience => Patience::default().diff(old, new),
+        EngineKind::Histogram => Histogram::default().diff(old, new),
+        EngineKind::Tree => TreeDiff::default().diff(old, new),
+    };
     RichHunks::try_from(edit_script)
 }
 
@@ -894,6 +1444,133 @@ mod tests {
         p_assert_eq!(expected, edit_script);
     }
 
+    // NOTE: this has to be gated behind the 'static-grammar-libs' cargo feature, otherwise the
+    // grammar isn't available to parse the documents.
+    #[cfg(feature = "static-grammar-libs")]
+    #[test_case(16, 1 ; "moved function")]
+    #[test_case(1000, 0 ; "shorter than the minimum")]
+    fn find_moved_function(min_entries: usize, expected_moves: usize) {
+        use crate::{
+            input_processing::TreeSitterProcessor,
+            parse::{parse_text, GrammarConfig},
+        };
+
+        let moved = "fn helper(x: i32) -> i32 {\n    let y = x * 2;\n    y + 1\n}\n";
+        let unchanged = "fn main() {\n    println!(\"{}\", helper(3));\n}\n";
+        let old_text = format!("{moved}\n{unchanged}");
+        let new_text = format!("{unchanged}\n{moved}");
+        let old_tree = parse_text(&old_text, "rust", &GrammarConfig::default()).unwrap();
+        let new_tree = parse_text(&new_text, "rust", &GrammarConfig::default()).unwrap();
+        let processor = TreeSitterProcessor::default();
+        let old_entries = processor.process(&old_tree, &old_text);
+        let new_entries = processor.process(&new_tree, &new_text);
+        let hunks = compute_edit_script(&old_entries, &new_entries).unwrap();
+
+        let moves = find_moves(&hunks, min_entries);
+        p_assert_eq!(moves.len(), expected_moves);
+        for m in &moves {
+            assert!(matches!(hunks.0[m.old_hunk], DocumentType::Old(_)));
+            assert!(matches!(hunks.0[m.new_hunk], DocumentType::New(_)));
+            p_assert_eq!(m.source.end.row - m.source.start.row, 2);
+            p_assert_eq!(m.destination.end.row - m.destination.start.row, 2);
+        }
+    }
+
+    // NOTE: this has to be gated behind the 'static-grammar-libs' cargo feature, otherwise the
+    // grammar isn't available to parse the documents.
+    #[cfg(feature = "static-grammar-libs")]
+    #[test]
+    fn group_overlapping_hunks() {
+        use crate::{
+            input_processing::TreeSitterProcessor,
+            parse::{parse_text, GrammarConfig},
+        };
+
+        let old_text = "fn a() {\n    x();\n}\n\nfn b() {\n    y();\n}\n";
+        let new_text = "fn a() {\n    z();\n}\n\nfn b() {\n    w();\n}\n";
+        let old_tree = parse_text(old_text, "rust", &GrammarConfig::default()).unwrap();
+        let new_tree = parse_text(new_text, "rust", &GrammarConfig::default()).unwrap();
+        let processor = TreeSitterProcessor::default();
+        let old_entries = processor.process(&old_tree, old_text);
+        let new_entries = processor.process(&new_tree, new_text);
+        let hunks = compute_edit_script(&old_entries, &new_entries).unwrap();
+
+        let groups = group_hunks(&hunks, &old_entries, &new_entries);
+        p_assert_eq!(groups.len(), 2);
+        for (group, line) in groups.iter().zip([1, 5]) {
+            // Each group has the deletion followed by the addition on the same line
+            let hunks: Vec<_> = group.iter().map(|&hunk_idx| &hunks.0[hunk_idx]).collect();
+            assert!(matches!(
+                hunks[..],
+                [DocumentType::Old(_), DocumentType::New(_)]
+            ));
+            for hunk in hunks {
+                p_assert_eq!(hunk.as_ref().first_line(), Some(line));
+            }
+        }
+    }
+
+    #[test]
+    fn increasing_pairs() {
+        let pairs = [(0, 3), (1, 0), (2, 1), (3, 4), (4, 2), (5, 5)];
+        p_assert_eq!(
+            longest_increasing_pairs(&pairs),
+            vec![(1, 0), (2, 1), (4, 2), (5, 5)]
+        );
+    }
+
+    /// Check that an edit script turns `a` into `b`.
+    ///
+    /// The elements of `a` that weren't deleted must be the same as the elements of `b` that
+    /// weren't added.
+    fn assert_valid_edit_script<T: PartialEq + Debug>(a: &[T], b: &[T], edits: &[EditType<&T>]) {
+        let is_edited = |elem: &T| {
+            edits.iter().any(|edit| match edit {
+                EditType::Addition(x) | EditType::Deletion(x) => std::ptr::eq(*x, elem),
+            })
+        };
+        let kept_a: Vec<_> = a.iter().filter(|x| !is_edited(x)).collect();
+        let kept_b: Vec<_> = b.iter().filter(|x| !is_edited(x)).collect();
+        p_assert_eq!(kept_a, kept_b);
+    }
+
+    #[test_case(b"", b"" ; "empty inputs")]
+    #[test_case(b"ABC", b"" ; "delete everything")]
+    #[test_case(b"", b"ABC" ; "add everything")]
+    #[test_case(b"ABCABBA", b"CBABAC" ; "no unique elements")]
+    #[test_case(b"ABCD", b"DABC" ; "moved element")]
+    #[test_case(b"xAyBz", b"uAvBw" ; "unique anchors")]
+    #[test_case(b"aaXbbYccZ", b"ccZaaXbbY" ; "reordered runs")]
+    fn patience_and_histogram_diffs_are_valid(a: &[u8], b: &[u8]) {
+        assert_valid_edit_script(a, b, &Patience::default().diff(a, b));
+        assert_valid_edit_script(a, b, &Histogram::default().diff(a, b));
+    }
+
+    #[test]
+    fn patience_aligns_on_unique_elements() {
+        // Myers matches the closing braces, but patience matches the unique function names
+        let a = ["f", "{", "x", "}", "g", "{", "y", "}"];
+        let b = ["g", "{", "y", "}", "f", "{", "x", "}"];
+        let edits = Patience::default().diff(&a[..], &b[..]);
+        assert_valid_edit_script(&a, &b, &edits);
+        p_assert_eq!(edits.len(), 8);
+    }
+
+    #[test]
+    fn histogram_prefers_rare_elements() {
+        let a = [1, 0, 0, 2, 0, 0];
+        let b = [0, 0, 2, 0, 0, 1];
+        let region = Histogram::find_region(&a, 0..a.len(), &b, 0..b.len()).unwrap();
+        p_assert_eq!(
+            region,
+            CommonRegion {
+                start: Coordinates { old: 1, new: 0 },
+                len: 5,
+                occurrences: 1,
+            }
+        );
+    }
+
     #[test_case(b"BAAA", b"CAAA" => 0 ; "no common prefix")]
     #[test_case(b"AAABA", b"AAACA" => 3 ; "with common prefix")]
     fn common_prefix(a: &[u8], b: &[u8]) -> usize {
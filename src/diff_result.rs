@@ -0,0 +1,260 @@
+//! An owned representation of a diff.
+//!
+//! The diff types in [`diff`](crate::diff) borrow the parsed syntax trees and source text, which
+//! means they can't outlive the documents they were computed from. The types in this module own
+//! all of their data, so a diff can be stored, cached, sent to another thread, or serialized and
+//! deserialized without keeping the documents or syntax trees around.
+
+use crate::{
+    diff::{self, DocumentType, Move},
+    input_processing,
+    render::{DisplayData, DocumentDiffData},
+    symbols::Symbol,
+};
+use serde::{Deserialize, Serialize};
+
+/// The result of diffing two documents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiffResult {
+    /// The old document
+    pub old: Document,
+    /// The new document
+    pub new: Document,
+    /// The hunks that make up the diff, in the order they were produced by the diff engine
+    pub hunks: Vec<DocumentType<Hunk>>,
+    /// The blocks of code that were moved, which refer to hunks in `hunks`
+    #[serde(default)]
+    pub moves: Vec<Move>,
+    /// The symbols that enclose each hunk, from the outermost to the innermost, in the same order
+    /// as `hunks`
+    #[serde(default)]
+    pub symbols: Vec<Vec<Symbol>>,
+    /// The hunks that overlap in the edit script, as groups of indices into `hunks`
+    ///
+    /// Each group has its old hunks followed by its new hunks, and the groups are in the order
+    /// they appear in the edit script.
+    #[serde(default)]
+    pub groups: Vec<Vec<usize>>,
+}
+
+/// A document that was diffed
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Document {
+    /// The filename of the document
+    pub filename: String,
+    /// The full text of the document
+    pub text: String,
+    /// The language the document was parsed with, like `rust`
+    #[serde(default)]
+    pub language: String,
+}
+
+/// A grouping of consecutive edited lines in a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Hunk {
+    /// The lines in the hunk, in ascending order
+    pub lines: Vec<Line>,
+}
+
+/// The edits on a single line of a document
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Line {
+    /// The index of the line in the document, starting from 0
+    pub line_index: usize,
+    /// The edited entries on the line
+    pub entries: Vec<Entry>,
+}
+
+/// An edited piece of text that corresponds to a syntax node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Entry {
+    /// The text of the entry
+    pub text: String,
+    /// The kind of the syntax node the entry comes from, like `identifier`
+    pub kind: String,
+    /// The numeric ID of the kind of syntax node, which is specific to the grammar
+    pub kind_id: u16,
+    /// The position where the entry starts
+    pub start_position: Position,
+    /// The position where the entry ends (exclusive)
+    pub end_position: Position,
+    /// The byte offset in the document where the entry starts
+    pub start_byte: usize,
+    /// The byte offset in the document where the entry ends (exclusive)
+    pub end_byte: usize,
+}
+
+/// A position in a document
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Position {
+    /// The row, starting from 0
+    pub row: usize,
+    /// The byte offset from the start of the row
+    pub column: usize,
+}
+
+impl From<tree_sitter::Point> for Position {
+    fn from(point: tree_sitter::Point) -> Self {
+        Position {
+            row: point.row,
+            column: point.column,
+        }
+    }
+}
+
+impl DiffResult {
+    /// Whether the documents have any differences
+    #[must_use]
+    pub fn has_changes(&self) -> bool {
+        !self.hunks.is_empty()
+    }
+}
+
+impl From<&DisplayData<'_>> for DiffResult {
+    fn from(data: &DisplayData) -> Self {
+        let old_line_starts = line_starts(data.old.text);
+        let new_line_starts = line_starts(data.new.text);
+        let hunks = data
+            .hunks
+            .0
+            .iter()
+            .map(|hunk| match hunk {
+                DocumentType::Old(hunk) => DocumentType::Old(Hunk::new(hunk, &old_line_starts)),
+                DocumentType::New(hunk) => DocumentType::New(Hunk::new(hunk, &new_line_starts)),
+            })
+            .collect();
+        DiffResult {
+            old: (&data.old).into(),
+            new: (&data.new).into(),
+            hunks,
+            moves: data.moves.clone(),
+            symbols: data.symbols.clone(),
+            groups: data.groups.clone(),
+        }
+    }
+}
+
+impl From<&DocumentDiffData<'_>> for Document {
+    fn from(data: &DocumentDiffData) -> Self {
+        Document {
+            filename: data.filename.to_string(),
+            text: data.text.to_string(),
+            language: data.language.to_string(),
+        }
+    }
+}
+
+impl Hunk {
+    /// Create an owned hunk, using the line start offsets of its document to compute byte offsets.
+    fn new(hunk: &diff::Hunk, line_starts: &[usize]) -> Self {
+        let lines = hunk
+            .0
+            .iter()
+            .map(|line| Line {
+                line_index: line.line_index,
+                entries: line
+                    .entries
+                    .iter()
+                    .map(|entry| Entry::new(entry, line_starts))
+                    .collect(),
+            })
+            .collect();
+        Hunk { lines }
+    }
+}
+
+impl Entry {
+    /// Create an owned entry, using the line start offsets of its document to compute byte
+    /// offsets.
+    fn new(entry: &input_processing::Entry, line_starts: &[usize]) -> Self {
+        let start_position = Position::from(entry.start_position());
+        let end_position = Position::from(entry.end_position());
+        Entry {
+            text: entry.text.to_string(),
+            kind: entry.reference.kind().to_string(),
+            kind_id: entry.kind_id,
+            start_position,
+            end_position,
+            start_byte: byte_offset(line_starts, start_position),
+            end_byte: byte_offset(line_starts, end_position),
+        }
+    }
+}
+
+/// Get the byte offset where each line in a text starts.
+///
+/// Lines are separated by `\n`, which matches how tree-sitter counts rows.
+fn line_starts(text: &str) -> Vec<usize> {
+    std::iter::once(0)
+        .chain(text.match_indices('\n').map(|(idx, _)| idx + 1))
+        .collect()
+}
+
+/// Convert a position to a byte offset, given the byte offset where each line starts.
+fn byte_offset(line_starts: &[usize], position: Position) -> usize {
+    line_starts[position.row] + position.column
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use test_case::test_case;
+
+    #[test_case("", &[0])]
+    #[test_case("abc", &[0])]
+    #[test_case("abc\n", &[0, 4])]
+    #[test_case("a\nbc\n\nd", &[0, 2, 5, 6])]
+    fn test_line_starts(text: &str, expected: &[usize]) {
+        assert_eq!(line_starts(text), expected);
+    }
+
+    #[test]
+    fn test_byte_offset() {
+        let text = "fn main() {\n    let x = 1;\n}\n";
+        let starts = line_starts(text);
+        let offset = byte_offset(&starts, Position { row: 1, column: 8 });
+        assert_eq!(&text[offset..offset + 1], "x");
+    }
+
+    #[test]
+    fn test_diff_result_is_send_and_sync() {
+        fn assert_send_sync<T: Send + Sync + 'static>() {}
+        assert_send_sync::<DiffResult>();
+    }
+
+    // NOTE: this has to be gated behind the 'static-grammar-libs' cargo feature, otherwise the
+    // grammar isn't available to parse the documents.
+    #[cfg(feature = "static-grammar-libs")]
+    #[test]
+    fn test_diff_result_round_trip() {
+        use crate::{Differ, Source};
+
+        let source = |name: &str, text: &str| Source::Text {
+            name: name.into(),
+            text: text.into(),
+        };
+        let session = Differ::default()
+            .diff(
+                source("a.rs", "fn main() {\n    let x = 1;\n}\n"),
+                source("b.rs", "fn main() {\n    let y = 1;\n}\n"),
+            )
+            .unwrap();
+        let result = session.diff().unwrap();
+        assert!(result.has_changes());
+
+        for hunk in &result.hunks {
+            let text = match hunk {
+                DocumentType::Old(_) => &result.old.text,
+                DocumentType::New(_) => &result.new.text,
+            };
+            for entry in hunk.as_ref().lines.iter().flat_map(|line| &line.entries) {
+                assert_eq!(&text[entry.start_byte..entry.end_byte], entry.text);
+                assert_eq!(entry.kind, "identifier");
+            }
+        }
+        let serialized = serde_json::to_string(&result).unwrap();
+        let deserialized: DiffResult = serde_json::from_str(&serialized).unwrap();
+        assert_eq!(deserialized, result);
+    }
+}
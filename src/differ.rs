@@ -0,0 +1,377 @@
+//! A high level API for diffing documents.
+//!
+//! This is the recommended entry point for using diffsitter as a library. A [`Differ`] holds the
+//! configuration for parsing and processing documents, and diffing two [sources](Source) creates a
+//! [`DiffSession`] that owns the parsed documents. The session can then be used to inspect or
+//! render the diff.
+//!
+//! ```no_run
+//! use libdiffsitter::{config::Config, render::Renderers, Differ, Source};
+//!
+//! let differ = Differ::new(&Config::default());
+//! let session = differ
+//!     .diff(Source::Path("old.rs".into()), Source::Path("new.rs".into()))
+//!     .unwrap();
+//! session
+//!     .render(&Renderers::default(), &mut std::io::stdout(), None)
+//!     .unwrap();
+//! ```
+
+use crate::{
+    config::Config,
+    diff::{
+        compute_edit_script_with_engine, find_moves, group_hunks, DiffConfig, HunkInsertionError,
+    },
+    diff_result::DiffResult,
+    input_processing::{TreeSitterProcessor, VectorData},
+    parse::{self, lang_name_from_path, GrammarConfig, LoadingError},
+    render::{DisplayData, DocumentDiffData, RenderState, Renderer, Renderers},
+    symbols::hunk_symbols,
+    STDIN_PATH,
+};
+use console::Term;
+use std::{
+    fs,
+    io::{self, Read, Write},
+    path::{Path, PathBuf},
+};
+use thiserror::Error;
+
+/// The possible errors that can arise when computing or rendering a diff
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Failed to load a document")]
+    Loading(#[from] LoadingError),
+
+    #[error("Failed to construct the hunks for the diff")]
+    HunkInsertion(#[from] HunkInsertionError),
+
+    #[error("Some IO error was encountered")]
+    Io(#[from] io::Error),
+
+    #[error("Failed to render the diff: {0}")]
+    Render(anyhow::Error),
+}
+
+/// The source of a document to diff
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Source {
+    /// A file on disk
+    Path(PathBuf),
+    /// Standard input
+    Stdin,
+    /// Text that's already in memory, like an unsaved buffer in an editor
+    ///
+    /// `name` identifies the text in the output, it doesn't have to exist on disk. Its file
+    /// extension is used to determine the language of the text if a language isn't set.
+    Text { name: PathBuf, text: String },
+}
+
+impl Source {
+    /// Create a source from a path, where `-` refers to stdin.
+    #[must_use]
+    pub fn from_path(path: &Path) -> Self {
+        if path == Path::new(STDIN_PATH) {
+            Source::Stdin
+        } else {
+            Source::Path(path.to_path_buf())
+        }
+    }
+
+    /// The name that identifies the source
+    #[must_use]
+    pub fn name(&self) -> &Path {
+        match self {
+            Source::Path(path) => path,
+            Source::Stdin => Path::new(STDIN_PATH),
+            Source::Text { name, .. } => name,
+        }
+    }
+
+    /// Read the text of the source.
+    fn read(self) -> io::Result<(PathBuf, String)> {
+        match self {
+            Source::Path(path) => {
+                let text = fs::read_to_string(&path)?;
+                Ok((path, text))
+            }
+            Source::Stdin => {
+                let mut text = String::new();
+                io::stdin().read_to_string(&mut text)?;
+                Ok((STDIN_PATH.into(), text))
+            }
+            Source::Text { name, text } => Ok((name, text)),
+        }
+    }
+}
+
+/// Diffs documents with a particular configuration
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct Differ {
+    /// Options for loading grammars
+    grammar: GrammarConfig,
+    /// Options for processing the parsed documents
+    input_processing: TreeSitterProcessor,
+    /// Options for computing the diff
+    diff: DiffConfig,
+    /// The language to parse documents with, which overrides the language that's deduced from the
+    /// file extension
+    language: Option<String>,
+}
+
+impl Differ {
+    /// Create a differ that uses the grammar and input processing options from a config.
+    #[must_use]
+    pub fn new(config: &Config) -> Self {
+        Differ {
+            grammar: config.grammar.clone(),
+            input_processing: config.input_processing.clone(),
+            diff: config.diff.clone(),
+            language: None,
+        }
+    }
+
+    /// Parse every document with the given language, rather than deducing the language from the
+    /// file extension.
+    #[must_use]
+    pub fn with_language(mut self, language: impl Into<String>) -> Self {
+        self.language = Some(language.into());
+        self
+    }
+
+    /// Load and parse two documents so they can be diffed.
+    ///
+    /// If a language wasn't set, the language for each document is deduced from its file
+    /// extension. If a document doesn't have a file extension (like stdin), the extension of the
+    /// other document is used.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if either document can't be read or parsed.
+    pub fn diff(&self, old: Source, new: Source) -> Result<DiffSession, Error> {
+        let old_language = self.resolve_language(old.name(), new.name())?;
+        let new_language = self.resolve_language(new.name(), old.name())?;
+        Ok(DiffSession {
+            old: self.load(old, &old_language)?,
+            new: self.load(new, &new_language)?,
+            old_language,
+            new_language,
+            input_processing: self.input_processing.clone(),
+            diff: self.diff.clone(),
+        })
+    }
+
+    /// Determine the language to use for the document at `path`.
+    ///
+    /// `other_path` is the path of the document it's being compared with, which is used if `path`
+    /// doesn't have an extension.
+    fn resolve_language(&self, path: &Path, other_path: &Path) -> Result<String, LoadingError> {
+        if let Some(language) = &self.language {
+            return Ok(language.clone());
+        }
+        let language = match lang_name_from_path(path, &self.grammar) {
+            Err(LoadingError::NoFileExt(_)) if other_path.extension().is_some() => {
+                lang_name_from_path(other_path, &self.grammar)?
+            }
+            result => result?,
+        };
+        Ok(language.to_string())
+    }
+
+    /// Read and parse a document.
+    fn load(&self, source: Source, language: &str) -> Result<VectorData, Error> {
+        let (path, text) = source.read()?;
+        let tree = parse::parse_text(&text, language, &self.grammar)?;
+        Ok(VectorData { text, tree, path })
+    }
+}
+
+/// Two parsed documents that can be diffed
+///
+/// The session owns the text and syntax trees of both documents, which the diff data references.
+#[derive(Debug)]
+pub struct DiffSession {
+    old: VectorData,
+    new: VectorData,
+    /// The language the old document was parsed with
+    old_language: String,
+    /// The language the new document was parsed with
+    new_language: String,
+    input_processing: TreeSitterProcessor,
+    diff: DiffConfig,
+}
+
+impl DiffSession {
+    /// The old document
+    #[must_use]
+    pub fn old_document(&self) -> &VectorData {
+        &self.old
+    }
+
+    /// The new document
+    #[must_use]
+    pub fn new_document(&self) -> &VectorData {
+        &self.new
+    }
+
+    /// Compute the diff and pass it to `f`.
+    ///
+    /// The diff data references the documents in the session, so it can only be accessed inside
+    /// of the closure.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the hunks for the diff can't be constructed.
+    pub fn with_display_data<T>(&self, f: impl FnOnce(&DisplayData) -> T) -> Result<T, Error> {
+        let old_filename = self.old.path.to_string_lossy();
+        let new_filename = self.new.path.to_string_lossy();
+        let old_entries = self.input_processing.process_with_language(
+            &self.old.tree,
+            &self.old.text,
+            &self.old_language,
+        );
+        let new_entries = self.input_processing.process_with_language(
+            &self.new.tree,
+            &self.new.text,
+            &self.new_language,
+        );
+        let hunks = compute_edit_script_with_engine(self.diff.engine, &old_entries, &new_entries)?;
+        let moves = if self.diff.detect_moves {
+            find_moves(&hunks, self.diff.min_move_entries)
+        } else {
+            Vec::new()
+        };
+        let symbols = hunk_symbols(
+            &hunks,
+            (&self.old.text, &self.old_language),
+            (&self.new.text, &self.new_language),
+        );
+        let groups = group_hunks(&hunks, &old_entries, &new_entries);
+        let data = DisplayData {
+            hunks,
+            old: DocumentDiffData {
+                filename: &old_filename,
+                text: &self.old.text,
+                language: &self.old_language,
+            },
+            new: DocumentDiffData {
+                filename: &new_filename,
+                text: &self.new.text,
+                language: &self.new_language,
+            },
+            moves,
+            symbols,
+            groups,
+        };
+        Ok(f(&data))
+    }
+
+    /// Compute the diff as an owned [`DiffResult`].
+    ///
+    /// Unlike [`with_display_data`](Self::with_display_data), the result doesn't reference the
+    /// documents in the session, so it can outlive the session.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the hunks for the diff can't be constructed.
+    pub fn diff(&self) -> Result<DiffResult, Error> {
+        self.with_display_data(|data| DiffResult::from(data))
+    }
+
+    /// Whether the documents have any differences.
+    ///
+    /// This computes the diff without rendering it.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the hunks for the diff can't be constructed.
+    pub fn has_changes(&self) -> Result<bool, Error> {
+        self.with_display_data(|data| data.has_changes())
+    }
+
+    /// Render the diff with the given renderer, returning whether the documents have any
+    /// differences.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the hunks for the diff can't be constructed or if the renderer
+    /// fails.
+    pub fn render(
+        &self,
+        renderer: &Renderers,
+        writer: &mut dyn Write,
+        term_info: Option<&Term>,
+    ) -> Result<bool, Error> {
+        self.with_display_data(|data| {
+            renderer
+                .render(writer, data, term_info)
+                .map(|()| data.has_changes())
+        })?
+        .map_err(Error::Render)
+    }
+
+    /// Render the diff as part of an output that contains multiple diffs, returning whether the
+    /// documents have any differences.
+    ///
+    /// The output has to be started with [`Renderer::begin`] and finished with [`Renderer::end`],
+    /// using the same `state` for every call.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the hunks for the diff can't be constructed or if the renderer
+    /// fails.
+    pub fn render_part(
+        &self,
+        renderer: &Renderers,
+        writer: &mut dyn Write,
+        term_info: Option<&Term>,
+        state: &mut RenderState,
+    ) -> Result<bool, Error> {
+        self.with_display_data(|data| {
+            renderer
+                .render_part(writer, data, term_info, state)
+                .map(|()| data.has_changes())
+        })?
+        .map_err(Error::Render)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[test_case("-", Source::Stdin)]
+    #[test_case("a.rs", Source::Path("a.rs".into()))]
+    #[test_case("./-", Source::Path("./-".into()))]
+    fn test_source_from_path(path: &str, expected: Source) {
+        assert_eq!(Source::from_path(Path::new(path)), expected);
+    }
+
+    #[test]
+    fn test_resolve_language_override() {
+        let differ = Differ::default().with_language("python");
+        let language = differ
+            .resolve_language(Path::new("a.rs"), Path::new("b.rs"))
+            .unwrap();
+        assert_eq!(language, "python");
+    }
+
+    #[test_case("a.rs", "b.py", "rust")]
+    #[test_case("-", "b.py", "python")]
+    #[test_case("/dev/null", "b.rs", "rust")]
+    fn test_resolve_language(path: &str, other_path: &str, expected: &str) {
+        let differ = Differ::default();
+        let language = differ
+            .resolve_language(Path::new(path), Path::new(other_path))
+            .unwrap();
+        assert_eq!(language, expected);
+    }
+
+    #[test]
+    fn test_resolve_language_no_ext() {
+        let differ = Differ::default();
+        let result = differ.resolve_language(Path::new("-"), Path::new("Makefile"));
+        assert!(matches!(result, Err(LoadingError::NoFileExt(_))));
+    }
+}
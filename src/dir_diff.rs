@@ -0,0 +1,143 @@
+//! Utilities for diffing two directories.
+//!
+//! Files in the two directories are paired up by their path relative to the root of each
+//! directory, so the caller can diff each pair of files individually.
+
+use std::{
+    collections::BTreeSet,
+    fs, io,
+    path::{Path, PathBuf},
+};
+
+/// A file that exists in at least one of the directories being compared
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FilePair {
+    /// The path of the file relative to the root of the directories
+    pub relative_path: PathBuf,
+    /// The path to the file in the old directory, if it exists there
+    pub old: Option<PathBuf>,
+    /// The path to the file in the new directory, if it exists there
+    pub new: Option<PathBuf>,
+}
+
+/// Pair up the files in two directories by their relative path.
+///
+/// Both directories are traversed recursively. The pairs are sorted by their relative path so the
+/// output is deterministic. Symlinks to directories are not followed, to avoid cycles.
+///
+/// # Errors
+///
+/// This returns an error if either directory (or any directory nested in them) can't be read.
+pub fn pair_files(old_dir: &Path, new_dir: &Path) -> io::Result<Vec<FilePair>> {
+    let old_files = relative_file_paths(old_dir)?;
+    let new_files = relative_file_paths(new_dir)?;
+
+    let pairs = old_files
+        .union(&new_files)
+        .map(|relative_path| FilePair {
+            old: old_files
+                .contains(relative_path)
+                .then(|| old_dir.join(relative_path)),
+            new: new_files
+                .contains(relative_path)
+                .then(|| new_dir.join(relative_path)),
+            relative_path: relative_path.clone(),
+        })
+        .collect();
+    Ok(pairs)
+}
+
+/// Collect the paths of every file in a directory, relative to that directory.
+fn relative_file_paths(root: &Path) -> io::Result<BTreeSet<PathBuf>> {
+    let mut paths = BTreeSet::new();
+    let mut pending_dirs = vec![root.to_path_buf()];
+
+    while let Some(dir) = pending_dirs.pop() {
+        for dir_entry in fs::read_dir(&dir)? {
+            let path = dir_entry?.path();
+            // `symlink_metadata` doesn't follow symlinks, so a symlink to a directory is neither a
+            // file nor a directory here.
+            let file_type = fs::symlink_metadata(&path)?.file_type();
+
+            if file_type.is_dir() {
+                pending_dirs.push(path);
+            } else if path.is_file() {
+                // Every path we traverse is nested in `root`, so this can't fail
+                let relative_path = path.strip_prefix(root).unwrap().to_path_buf();
+                paths.insert(relative_path);
+            }
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::env;
+
+    fn test_data_dir(name: &str) -> PathBuf {
+        [
+            env::var("CARGO_MANIFEST_DIR").unwrap().as_str(),
+            "test_data",
+            name,
+        ]
+        .iter()
+        .collect()
+    }
+
+    #[test]
+    fn test_pair_files() {
+        let old_dir = test_data_dir("short");
+        let new_dir = test_data_dir("medium");
+        let pairs = pair_files(&old_dir, &new_dir).unwrap();
+
+        let summary: Vec<_> = pairs
+            .iter()
+            .map(|pair| {
+                (
+                    pair.relative_path.to_string_lossy().replace('\\', "/"),
+                    pair.old.is_some(),
+                    pair.new.is_some(),
+                )
+            })
+            .collect();
+        let expected: Vec<_> = [
+            ("cpp/a.cpp", false, true),
+            ("cpp/b.cpp", false, true),
+            ("go/a.go", true, false),
+            ("go/b.go", true, false),
+            ("markdown/a.md", true, false),
+            ("markdown/b.md", true, false),
+            ("python/a.py", true, false),
+            ("python/b.py", true, false),
+            ("rust/a.rs", true, true),
+            ("rust/b.rs", true, true),
+        ]
+        .into_iter()
+        .map(|(path, old, new)| (path.to_string(), old, new))
+        .collect();
+        assert_eq!(summary, expected);
+    }
+
+    #[test]
+    fn test_pair_files_joins_paths() {
+        let old_dir = test_data_dir("short");
+        let new_dir = test_data_dir("medium");
+        let pairs = pair_files(&old_dir, &new_dir).unwrap();
+        let rust_pair = pairs
+            .iter()
+            .find(|pair| pair.relative_path == Path::new("rust").join("a.rs"))
+            .unwrap();
+        assert_eq!(rust_pair.old, Some(old_dir.join("rust").join("a.rs")));
+        assert_eq!(rust_pair.new, Some(new_dir.join("rust").join("a.rs")));
+    }
+
+    #[test]
+    fn test_pair_files_missing_dir() {
+        let old_dir = test_data_dir("short");
+        let new_dir = test_data_dir("does_not_exist");
+        assert!(pair_files(&old_dir, &new_dir).is_err());
+    }
+}
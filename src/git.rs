@@ -0,0 +1,499 @@
+//! Utilities for diffing files that come from git.
+//!
+//! There are two ways to get files from git. Git can invoke diffsitter as an external diff program
+//! (see `GIT_EXTERNAL_DIFF` in `git(1)`), in which case git passes the files and their metadata as
+//! arguments. Alternatively, we can compare two revisions by reading the blobs directly from the
+//! repository with the `git` command.
+
+use std::{
+    fs, io,
+    path::{Path, PathBuf},
+    process::Command,
+    str::Utf8Error,
+};
+use thiserror::Error;
+
+/// The path git uses for a file that doesn't exist
+const DEV_NULL: &str = "/dev/null";
+
+/// The placeholder git passes to external diff programs as the hex and mode of a missing file
+const MISSING_PLACEHOLDER: &str = ".";
+
+/// The file mode git reports for a gitlink (a submodule)
+const GITLINK_MODE: &str = "160000";
+
+/// The number of characters to use for abbreviated object IDs in headers
+const SHORT_HEX_LEN: usize = 7;
+
+/// The number of arguments git passes to an external diff program
+const EXTERNAL_DIFF_NUM_ARGS: usize = 7;
+
+/// The number of arguments git passes to an external diff program for renames and copies
+const EXTERNAL_DIFF_NUM_ARGS_RENAME: usize = 9;
+
+/// The possible errors that can arise when reading files from git
+#[derive(Error, Debug)]
+pub enum Error {
+    #[error("Expected {EXTERNAL_DIFF_NUM_ARGS} or {EXTERNAL_DIFF_NUM_ARGS_RENAME} arguments from git, got {0}")]
+    InvalidArgCount(usize),
+
+    #[error("Failed to run `git {args}`: {stderr}")]
+    CommandFailed { args: String, stderr: String },
+
+    #[error("Unexpected output from git: {0}")]
+    MalformedOutput(String),
+
+    #[error("The contents of {0} are not valid UTF-8")]
+    InvalidUtf8(PathBuf, #[source] Utf8Error),
+
+    #[error("Some IO error was encountered")]
+    IoError(#[from] io::Error),
+}
+
+/// One side of a diff of a file from git
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitDocument {
+    /// The path of the file in the repository
+    pub path: PathBuf,
+    /// The object ID of the blob, which is all zeros if it's unknown
+    pub hex: String,
+    /// The file mode, like `100644`
+    pub mode: String,
+    /// The contents of the file, or `None` if the file doesn't exist on this side of the diff
+    ///
+    /// The contents are kept as bytes because files that can't be parsed, like images, don't have
+    /// to be valid UTF-8.
+    pub contents: Option<Vec<u8>>,
+    /// A file on disk that has the contents of the document, if there is one
+    ///
+    /// This is used to invoke the fallback diff command with files git has already written out.
+    pub file: Option<PathBuf>,
+}
+
+impl GitDocument {
+    /// Whether the file exists on this side of the diff
+    #[must_use]
+    pub fn exists(&self) -> bool {
+        self.contents.is_some()
+    }
+
+    /// The contents of the file as text, which is empty if the file doesn't exist.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if the contents aren't valid UTF-8.
+    pub fn text(&self) -> Result<&str, Error> {
+        match &self.contents {
+            Some(bytes) => {
+                std::str::from_utf8(bytes).map_err(|e| Error::InvalidUtf8(self.path.clone(), e))
+            }
+            None => Ok(""),
+        }
+    }
+
+    /// The abbreviated object ID for the file
+    #[must_use]
+    pub fn short_hex(&self) -> &str {
+        &self.hex[..self.hex.len().min(SHORT_HEX_LEN)]
+    }
+
+    /// The name to display for the document, using `prefix` like git does (`a/` or `b/`).
+    ///
+    /// Files that don't exist are displayed as `/dev/null`.
+    #[must_use]
+    pub fn display_name(&self, prefix: &str) -> String {
+        if self.exists() {
+            format!("{prefix}{}", self.path.display())
+        } else {
+            DEV_NULL.into()
+        }
+    }
+}
+
+/// The diff of a single file from git
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GitFileDiff {
+    pub old: GitDocument,
+    pub new: GitDocument,
+    /// Extra information git supplies about a rename or copy, like the similarity index
+    pub rename_info: Option<String>,
+}
+
+impl GitFileDiff {
+    /// Create a file diff from the arguments git passes to an external diff program.
+    ///
+    /// Git passes `path old-file old-hex old-mode new-file new-hex new-mode`, with two extra
+    /// arguments (`new-path rename-info`) if the file was renamed or copied.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if there's an unexpected number of arguments or if the files that
+    /// git supplied can't be read.
+    pub fn from_external_diff_args(args: &[String]) -> Result<Self, Error> {
+        let (new_path, rename_info) = match args.len() {
+            EXTERNAL_DIFF_NUM_ARGS => (&args[0], None),
+            EXTERNAL_DIFF_NUM_ARGS_RENAME => (&args[7], Some(args[8].clone())),
+            n => return Err(Error::InvalidArgCount(n)),
+        };
+        Ok(GitFileDiff {
+            old: external_diff_document(&args[0], &args[1], &args[2], &args[3])?,
+            new: external_diff_document(new_path, &args[4], &args[5], &args[6])?,
+            rename_info,
+        })
+    }
+
+    /// The path that should be used to determine the language of the file
+    #[must_use]
+    pub fn path(&self) -> &Path {
+        if self.new.exists() {
+            &self.new.path
+        } else {
+            &self.old.path
+        }
+    }
+
+    /// Generate the lines of a header that describes the change, in the style of `git diff`.
+    #[must_use]
+    pub fn header(&self) -> Vec<String> {
+        let mut lines = vec![format!(
+            "diff --git a/{} b/{}",
+            self.old.path.display(),
+            self.new.path.display()
+        )];
+        let mut index = format!("index {}..{}", self.old.short_hex(), self.new.short_hex());
+
+        match (self.old.exists(), self.new.exists()) {
+            (false, _) => lines.push(format!("new file mode {}", self.new.mode)),
+            (_, false) => lines.push(format!("deleted file mode {}", self.old.mode)),
+            _ if self.old.mode != self.new.mode => {
+                lines.push(format!("old mode {}", self.old.mode));
+                lines.push(format!("new mode {}", self.new.mode));
+            }
+            _ => index = format!("{index} {}", self.new.mode),
+        }
+        if let Some(rename_info) = &self.rename_info {
+            lines.extend(rename_info.lines().map(str::to_string));
+        }
+        lines.push(index);
+        lines
+    }
+}
+
+/// Create a document from the arguments git passes to an external diff program for one file.
+fn external_diff_document(
+    path: &str,
+    file: &str,
+    hex: &str,
+    mode: &str,
+) -> Result<GitDocument, Error> {
+    let exists = file != DEV_NULL && mode != MISSING_PLACEHOLDER;
+    let contents = if exists { Some(fs::read(file)?) } else { None };
+    Ok(GitDocument {
+        path: path.into(),
+        hex: if hex == MISSING_PLACEHOLDER {
+            zero_hex(SHORT_HEX_LEN)
+        } else {
+            hex.into()
+        },
+        mode: mode.into(),
+        contents,
+        file: Some(file.into()),
+    })
+}
+
+/// Diff every file that changed between two revisions of the repository in the current directory.
+///
+/// The file contents are read directly from the repository, so neither revision needs to be
+/// checked out. Submodules are skipped.
+///
+/// # Errors
+///
+/// This returns an error if git fails.
+pub fn diff_revisions(old_rev: &str, new_rev: &str) -> Result<Vec<GitFileDiff>, Error> {
+    let raw_diff = run_git(&[
+        "diff",
+        "--raw",
+        "-z",
+        "--no-abbrev",
+        "--no-renames",
+        // Revisions that start with a dash would otherwise be read as options, like `--output`
+        "--end-of-options",
+        old_rev,
+        new_rev,
+    ])?;
+    let raw_diff = String::from_utf8(raw_diff)
+        .map_err(|_| Error::MalformedOutput("the diff is not valid UTF-8".into()))?;
+
+    parse_raw_diff(&raw_diff)?
+        .into_iter()
+        .filter(|change| change.old_mode != GITLINK_MODE && change.new_mode != GITLINK_MODE)
+        .map(|change| {
+            Ok(GitFileDiff {
+                old: blob_document(change.path, change.old_hex, change.old_mode)?,
+                new: blob_document(change.path, change.new_hex, change.new_mode)?,
+                rename_info: None,
+            })
+        })
+        .collect()
+}
+
+/// A changed file from the output of `git diff --raw`
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct RawChange<'a> {
+    old_mode: &'a str,
+    new_mode: &'a str,
+    old_hex: &'a str,
+    new_hex: &'a str,
+    path: &'a str,
+}
+
+/// Parse the output of `git diff --raw -z --no-renames`.
+///
+/// Each change is a metadata field (`:old-mode new-mode old-hex new-hex status`) followed by the
+/// path, with every field terminated by a NUL byte.
+fn parse_raw_diff(raw_diff: &str) -> Result<Vec<RawChange<'_>>, Error> {
+    let mut fields = raw_diff.split_terminator('\0');
+    let mut changes = Vec::new();
+
+    while let Some(metadata) = fields.next() {
+        let malformed = || Error::MalformedOutput(metadata.to_string());
+        let metadata_fields: Vec<_> = metadata
+            .strip_prefix(':')
+            .ok_or_else(malformed)?
+            .split(' ')
+            .collect();
+        let [old_mode, new_mode, old_hex, new_hex, _status] = metadata_fields[..] else {
+            return Err(malformed());
+        };
+        let path = fields.next().ok_or_else(malformed)?;
+        changes.push(RawChange {
+            old_mode,
+            new_mode,
+            old_hex,
+            new_hex,
+            path,
+        });
+    }
+    Ok(changes)
+}
+
+/// Create a document with the contents of a blob from the repository.
+fn blob_document(path: &str, hex: &str, mode: &str) -> Result<GitDocument, Error> {
+    // Git uses an all-zero object ID for files that don't exist
+    let exists = hex.chars().any(|c| c != '0');
+    let contents = if exists {
+        Some(run_git(&["cat-file", "blob", hex])?)
+    } else {
+        None
+    };
+    Ok(GitDocument {
+        path: path.into(),
+        hex: hex.into(),
+        mode: mode.into(),
+        contents,
+        file: None,
+    })
+}
+
+/// Run a git command and return its stdout.
+fn run_git(args: &[&str]) -> Result<Vec<u8>, Error> {
+    let output = Command::new("git").args(args).output()?;
+    if !output.status.success() {
+        return Err(Error::CommandFailed {
+            args: args.join(" "),
+            stderr: String::from_utf8_lossy(&output.stderr).trim().to_string(),
+        });
+    }
+    Ok(output.stdout)
+}
+
+/// An all-zero object ID, which git uses to denote a missing object
+fn zero_hex(len: usize) -> String {
+    "0".repeat(len)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use std::env;
+
+    fn document(path: &str, hex: &str, mode: &str, text: Option<&str>) -> GitDocument {
+        GitDocument {
+            path: path.into(),
+            hex: hex.into(),
+            mode: mode.into(),
+            contents: text.map(|text| text.as_bytes().to_vec()),
+            file: None,
+        }
+    }
+
+    #[test]
+    fn test_parse_raw_diff() {
+        let old_hex = "1".repeat(40);
+        let new_hex = "2".repeat(40);
+        let zeros = zero_hex(40);
+        let raw_diff = format!(
+            ":100644 100755 {old_hex} {new_hex} M\0src/main.rs\0:000000 100644 {zeros} {new_hex} A\0new file.py\0"
+        );
+        let changes = parse_raw_diff(&raw_diff).unwrap();
+        assert_eq!(
+            changes,
+            vec![
+                RawChange {
+                    old_mode: "100644",
+                    new_mode: "100755",
+                    old_hex: &old_hex,
+                    new_hex: &new_hex,
+                    path: "src/main.rs",
+                },
+                RawChange {
+                    old_mode: "000000",
+                    new_mode: "100644",
+                    old_hex: &zeros,
+                    new_hex: &new_hex,
+                    path: "new file.py",
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_raw_diff_malformed() {
+        assert!(parse_raw_diff("100644 100644 abc def M\0path\0").is_err());
+        assert!(parse_raw_diff(":100644 100644 abc M\0path\0").is_err());
+        assert!(parse_raw_diff(":100644 100644 abc def M\0").is_err());
+    }
+
+    #[test]
+    fn test_external_diff_args_count() {
+        let args: Vec<String> = ["a.rs", "/dev/null", ".", "."]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        assert!(matches!(
+            GitFileDiff::from_external_diff_args(&args),
+            Err(Error::InvalidArgCount(4))
+        ));
+    }
+
+    #[test]
+    fn test_external_diff_missing_files() {
+        let args: Vec<String> = ["a.rs", "/dev/null", ".", ".", "/dev/null", ".", "."]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let file_diff = GitFileDiff::from_external_diff_args(&args).unwrap();
+        assert!(!file_diff.old.exists());
+        assert!(!file_diff.new.exists());
+        assert_eq!(file_diff.old.display_name("a/"), DEV_NULL);
+        assert_eq!(file_diff.old.short_hex(), "0000000");
+    }
+
+    #[test]
+    fn test_external_diff_binary_file() {
+        // Binary files are read without failing, they just can't be decoded as text
+        let file = [
+            env::var("CARGO_MANIFEST_DIR").unwrap().as_str(),
+            "test_data",
+            "binary",
+            "latin1.py",
+        ]
+        .iter()
+        .collect::<PathBuf>();
+        let file = file.to_string_lossy().to_string();
+        let args: Vec<String> = ["latin1.py", "/dev/null", ".", ".", &file, "abc", "100644"]
+            .iter()
+            .map(|s| s.to_string())
+            .collect();
+        let file_diff = GitFileDiff::from_external_diff_args(&args).unwrap();
+        assert!(file_diff.new.exists());
+        assert!(matches!(
+            file_diff.new.text(),
+            Err(Error::InvalidUtf8(path, _)) if path == Path::new("latin1.py")
+        ));
+        assert_eq!(file_diff.old.text().unwrap(), "");
+    }
+
+    #[test]
+    fn test_revision_is_not_an_option() {
+        // Tests run from the crate's directory, which is in the repository
+        let output_file = env::temp_dir().join(format!("diffsitter-output-{}", std::process::id()));
+        let output_option = format!("--output={}", output_file.display());
+        assert!(diff_revisions(&output_option, "HEAD").is_err());
+        assert!(!output_file.exists());
+    }
+
+    #[test]
+    fn test_header_modified() {
+        let file_diff = GitFileDiff {
+            old: document("src/lib.rs", "0123456789abcdef", "100644", Some("a")),
+            new: document("src/lib.rs", "fedcba9876543210", "100644", Some("b")),
+            rename_info: None,
+        };
+        assert_eq!(
+            file_diff.header(),
+            vec![
+                "diff --git a/src/lib.rs b/src/lib.rs",
+                "index 0123456..fedcba9 100644",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_header_mode_change() {
+        let file_diff = GitFileDiff {
+            old: document("run.sh", "0123456789abcdef", "100644", Some("a")),
+            new: document("run.sh", "fedcba9876543210", "100755", Some("b")),
+            rename_info: None,
+        };
+        assert_eq!(
+            file_diff.header(),
+            vec![
+                "diff --git a/run.sh b/run.sh",
+                "old mode 100644",
+                "new mode 100755",
+                "index 0123456..fedcba9",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_header_new_file() {
+        let file_diff = GitFileDiff {
+            old: document("a.py", &zero_hex(40), "000000", None),
+            new: document("a.py", "fedcba9876543210", "100644", Some("b")),
+            rename_info: None,
+        };
+        assert_eq!(
+            file_diff.header(),
+            vec![
+                "diff --git a/a.py b/a.py",
+                "new file mode 100644",
+                "index 0000000..fedcba9",
+            ]
+        );
+        assert_eq!(file_diff.path(), Path::new("a.py"));
+    }
+
+    #[test]
+    fn test_header_rename() {
+        let file_diff = GitFileDiff {
+            old: document("old.rs", "0123456789abcdef", "100644", Some("a")),
+            new: document("new.rs", "fedcba9876543210", "100644", Some("b")),
+            rename_info: Some(
+                "similarity index 90%\nrename from old.rs\nrename to new.rs\n".into(),
+            ),
+        };
+        assert_eq!(
+            file_diff.header(),
+            vec![
+                "diff --git a/old.rs b/new.rs",
+                "similarity index 90%",
+                "rename from old.rs",
+                "rename to new.rs",
+                "index 0123456..fedcba9 100644",
+            ]
+        );
+    }
+}
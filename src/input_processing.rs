@@ -3,16 +3,22 @@
 //! These methods handle preprocessing the input data so it can be fed into the diff engines to
 //! compute diff data.
 
+use crate::comments::CommentFilter;
+use crate::normalization::{NormalizationRules, Normalizer};
+use crate::parse::{generate_language, GrammarConfig};
+use log::{debug, error};
 use logging_timer::time;
 use serde::{Deserialize, Serialize};
 use std::borrow::Cow;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
 use std::hash::{Hash, Hasher};
-use std::ops::{Deref, DerefMut};
+use std::ops::{Deref, DerefMut, Range};
 use std::{cell::RefCell, ops::Index, path::PathBuf};
+use thiserror::Error;
 use tree_sitter::Node as TSNode;
 use tree_sitter::Point;
 use tree_sitter::Tree as TSTree;
+use tree_sitter::{Language, Query, QueryCursor, QueryError, StreamingIterator};
 use unicode_segmentation as us;
 
 #[cfg(test)]
@@ -46,12 +52,53 @@ pub struct TreeSitterProcessor {
     /// This is a set of strings that correspond to the tree sitter node types.
     pub include_kinds: Option<HashSet<String>>,
 
+    /// The scopes to exclude from processing. This takes precedence over `include_scopes`.
+    ///
+    /// A node is excluded if it or any of its ancestors matches one of the scopes.
+    pub exclude_scopes: Option<Vec<Scope>>,
+
+    /// The scopes to explicitly include when processing, like the body of a particular function.
+    /// The nodes in these scopes will be overridden by the scopes in `exclude_scopes`.
+    ///
+    /// A node is included if it or any of its ancestors matches one of the scopes.
+    pub include_scopes: Option<Vec<Scope>>,
+
+    /// Tree-sitter queries that select the regions of a document to include or exclude, keyed by
+    /// the language name
+    ///
+    /// These only apply when the language of the document is known.
+    pub queries: HashMap<String, QueryRules>,
+
+    /// Whether to ignore comments and docstrings.
+    ///
+    /// This only applies when the language of the document is known, since the kinds of comment
+    /// nodes are different for every language.
+    pub ignore_comments: bool,
+
+    /// The kinds of comment nodes for each language, keyed by the language name
+    ///
+    /// These are added to the comment kinds we already know about for the grammars diffsitter is
+    /// compiled with, which is useful for dynamically loaded grammars.
+    pub comment_kinds: HashMap<String, Vec<String>>,
+
     /// Whether to strip whitespace when processing node text.
     ///
     /// Whitespace includes whitespace characters and newlines. This can provide much more accurate
     /// diffs that do not account for line breaks. This is useful especially for more text heavy
     /// documents like markdown files.
     pub strip_whitespace: bool,
+
+    /// Normalization rules for syntax that code formatters commonly change
+    ///
+    /// These apply to every language, unless the language has its own rules in
+    /// `language_normalization`. Like the comment filters, they only apply when the language of
+    /// the document is known.
+    pub normalization: NormalizationRules,
+
+    /// Normalization rules for specific languages, keyed by the language name
+    ///
+    /// These take precedence over `normalization`.
+    pub language_normalization: HashMap<String, NormalizationRules>,
 }
 
 // TODO: if we want to do any string transformations we need to store Cow strings.
@@ -65,11 +112,191 @@ impl Default for TreeSitterProcessor {
             split_graphemes: true,
             exclude_kinds: None,
             include_kinds: None,
+            exclude_scopes: None,
+            include_scopes: None,
+            queries: HashMap::new(),
+            ignore_comments: false,
+            comment_kinds: HashMap::new(),
             strip_whitespace: true,
+            normalization: NormalizationRules::default(),
+            language_normalization: HashMap::new(),
+        }
+    }
+}
+
+/// A filter for the nodes that make up a scope, like a function or a struct
+///
+/// A node matches the scope if it matches both the kind and the name. If either of them isn't set,
+/// it matches any node.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct Scope {
+    /// The tree-sitter node kind of the scope, like `function_item`
+    pub kind: Option<String>,
+
+    /// The name of the scope, like the name of a function
+    ///
+    /// See [`scope_name`] for how the name of a node is determined.
+    pub name: Option<String>,
+}
+
+impl Scope {
+    /// Whether a node matches the scope.
+    fn matches(&self, node: TSNode, text: &str) -> bool {
+        self.kind.as_ref().is_none_or(|kind| node.kind() == kind)
+            && self
+                .name
+                .as_ref()
+                .is_none_or(|name| scope_name(node, text) == Some(name))
+    }
+}
+
+/// Get the name of a node that defines a scope, like a function or a struct.
+///
+/// This is the text of the node's `name` field, which is what most grammars use for definitions.
+/// Languages like C declare the name of a function in its `declarator` field instead, so the
+/// innermost declarator is used if the node doesn't have a name.
+#[must_use]
+pub fn scope_name<'a>(node: TSNode, text: &'a str) -> Option<&'a str> {
+    if let Some(name) = node.child_by_field_name("name") {
+        return text.get(name.byte_range());
+    }
+    let mut declarator = node.child_by_field_name("declarator")?;
+    while let Some(inner) = declarator.child_by_field_name("declarator") {
+        declarator = inner;
+    }
+    text.get(declarator.byte_range())
+}
+
+/// Tree-sitter queries that select regions of a document to include or exclude
+///
+/// Each query is an S-expression query, like `(line_comment) @comment`. The nodes that are
+/// captured by a query make up its regions, except for captures whose names start with an
+/// underscore, like `@_name`. Those can be used with predicates without selecting a region.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct QueryRules {
+    /// Queries for the regions to exclude. This takes precedence over `include`.
+    pub exclude: Vec<String>,
+
+    /// Queries for the regions to include. If this is empty, the whole document is included.
+    pub include: Vec<String>,
+}
+
+/// The possible errors that can arise when validating the query rules
+#[derive(Error, Debug)]
+pub enum QueryRulesError {
+    #[error("Invalid query for {language}")]
+    InvalidQuery {
+        language: String,
+        #[source]
+        source: QueryError,
+    },
+}
+
+/// A set of disjoint byte ranges in a document
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct Regions(Vec<Range<usize>>);
+
+impl Regions {
+    /// Create a set of regions, merging any ranges that overlap.
+    fn new(mut ranges: Vec<Range<usize>>) -> Self {
+        ranges.sort_unstable_by_key(|range| range.start);
+        let mut merged: Vec<Range<usize>> = Vec::with_capacity(ranges.len());
+
+        for range in ranges {
+            match merged.last_mut() {
+                Some(last) if range.start <= last.end => last.end = last.end.max(range.end),
+                _ => merged.push(range),
+            }
         }
+        Regions(merged)
+    }
+
+    /// Whether a byte range is entirely inside one of the regions.
+    fn contains(&self, range: &Range<usize>) -> bool {
+        let idx = self.0.partition_point(|region| region.start <= range.start);
+        idx > 0 && range.end <= self.0[idx - 1].end
     }
 }
 
+/// The regions selected by the query rules for a document
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+struct QueryRegions {
+    exclude: Regions,
+    /// This is `None` if there aren't any include queries, which means everything is included
+    include: Option<Regions>,
+}
+
+impl QueryRegions {
+    /// Whether a node is in the regions the user wants to process.
+    fn contains(&self, node: TSNode) -> bool {
+        let range = node.byte_range();
+        !self.exclude.contains(&range)
+            && self
+                .include
+                .as_ref()
+                .is_none_or(|include| include.contains(&range))
+    }
+}
+
+impl QueryRules {
+    /// Run the queries on a tree to find the regions they select.
+    ///
+    /// The queries are validated when the config is loaded, so a query that fails to compile is
+    /// logged and skipped.
+    fn regions(&self, tree: &TSTree, text: &str) -> QueryRegions {
+        let language = tree.language();
+        QueryRegions {
+            exclude: Regions::new(query_ranges(&self.exclude, &language, tree, text)),
+            include: (!self.include.is_empty())
+                .then(|| Regions::new(query_ranges(&self.include, &language, tree, text))),
+        }
+    }
+
+    /// Check that every query compiles for the given language.
+    fn validate(&self, language: &Language) -> Result<(), QueryError> {
+        for query in self.exclude.iter().chain(&self.include) {
+            Query::new(language, query)?;
+        }
+        Ok(())
+    }
+}
+
+/// Get the byte ranges of the nodes captured by a list of queries.
+fn query_ranges(
+    queries: &[String],
+    language: &Language,
+    tree: &TSTree,
+    text: &str,
+) -> Vec<Range<usize>> {
+    let mut ranges = Vec::new();
+    let mut cursor = QueryCursor::new();
+
+    for source in queries {
+        let query = match Query::new(language, source) {
+            Ok(query) => query,
+            Err(e) => {
+                error!("Skipping invalid query {source}: {e}");
+                continue;
+            }
+        };
+        let capture_names = query.capture_names();
+        let mut matches = cursor.matches(&query, tree.root_node(), text.as_bytes());
+
+        while let Some(query_match) = matches.next() {
+            ranges.extend(
+                query_match
+                    .captures
+                    .iter()
+                    .filter(|capture| !capture_names[capture.index as usize].starts_with('_'))
+                    .map(|capture| capture.node.byte_range()),
+            );
+        }
+    }
+    ranges
+}
+
 #[derive(Debug)]
 struct TSNodeWrapper<'a>(TSNode<'a>);
 
@@ -80,20 +307,122 @@ impl<'a> TSNodeTrait for TSNodeWrapper<'a> {
 }
 
 impl TreeSitterProcessor {
+    /// Process a tree without knowing the language it was parsed with.
+    ///
+    /// This only applies the options that don't depend on the language: the node kind filters,
+    /// the scopes, whitespace stripping and grapheme splitting. The queries, `ignore_comments` and
+    /// the normalization rules are keyed by language, so they're ignored here. Use
+    /// [`process_with_language`](Self::process_with_language) to apply every option.
     #[time("info", "ast::{}")]
     pub fn process<'a>(&self, tree: &'a TSTree, text: &'a str) -> Vec<Entry<'a>> {
+        self.process_leaves(tree, text, None)
+    }
+
+    /// Process a tree, applying the normalization rules and queries for the language the tree was
+    /// parsed with.
+    #[time("info", "ast::{}")]
+    pub fn process_with_language<'a>(
+        &self,
+        tree: &'a TSTree,
+        text: &'a str,
+        language: &str,
+    ) -> Vec<Entry<'a>> {
+        self.process_leaves(tree, text, Some(language))
+    }
+
+    /// Check that the queries for every language are valid.
+    ///
+    /// Languages whose grammars can't be loaded are skipped, since documents in those languages
+    /// can't be diffed anyway.
+    ///
+    /// # Errors
+    ///
+    /// This returns an error if any of the queries fails to compile.
+    pub fn validate(&self, grammar: &GrammarConfig) -> Result<(), QueryRulesError> {
+        let mut languages: Vec<_> = self.queries.keys().collect();
+        languages.sort();
+
+        for language in languages {
+            let ts_language = match generate_language(language, grammar) {
+                Ok(ts_language) => ts_language,
+                Err(e) => {
+                    debug!("Not validating the queries for {language}: {e}");
+                    continue;
+                }
+            };
+            self.queries[language]
+                .validate(&ts_language)
+                .map_err(|source| QueryRulesError::InvalidQuery {
+                    language: language.clone(),
+                    source,
+                })?;
+        }
+        Ok(())
+    }
+
+    /// Get the normalization rules that apply to a language.
+    #[must_use]
+    pub fn normalization_rules(&self, language: &str) -> NormalizationRules {
+        self.language_normalization
+            .get(language)
+            .copied()
+            .unwrap_or(self.normalization)
+    }
+
+    fn process_leaves<'a>(
+        &self,
+        tree: &'a TSTree,
+        text: &'a str,
+        language: Option<&str>,
+    ) -> Vec<Entry<'a>> {
+        let normalizer = language.and_then(|language| {
+            let rules = self.normalization_rules(language);
+            rules
+                .any_enabled()
+                .then(|| Normalizer::new(rules, language))
+        });
+        let normalizer = normalizer.as_ref();
+        let query_regions = language
+            .and_then(|language| self.queries.get(language))
+            .map(|rules| rules.regions(tree, text));
+        let comment_filter = language.filter(|_| self.ignore_comments).map(|language| {
+            let extra_kinds = self
+                .comment_kinds
+                .get(language)
+                .map_or(&[][..], Vec::as_slice);
+            CommentFilter::new(language, extra_kinds)
+        });
+
         let ast_vector = from_ts_tree(tree, text);
         let iter = ast_vector
             .leaves
             .iter()
-            .filter(|leaf| self.should_include_node(&TSNodeWrapper(leaf.reference)));
+            .filter(|leaf| self.should_include_node(&TSNodeWrapper(leaf.reference)))
+            .filter(|leaf| self.is_in_included_scope(leaf.reference, text))
+            .filter(|leaf| {
+                query_regions
+                    .as_ref()
+                    .is_none_or(|regions| regions.contains(leaf.reference))
+            })
+            .filter(|leaf| {
+                !comment_filter
+                    .as_ref()
+                    .is_some_and(|filter| filter.is_comment(leaf.reference))
+            })
+            .filter(|leaf| !normalizer.is_some_and(|n| n.is_ignored(leaf.reference)));
         // Splitting on graphemes generates a vector of entries instead of a direct mapping, which
         // is why we have the branching here
         if self.split_graphemes {
-            iter.flat_map(|leaf| leaf.split_on_graphemes(self.strip_whitespace))
-                .collect()
+            iter.flat_map(|&leaf| match normalized_entry(leaf, normalizer) {
+                Some(entry) => vec![entry],
+                None => leaf.split_on_graphemes(self.strip_whitespace),
+            })
+            .collect()
         } else {
-            iter.map(|&x| self.process_leaf(x)).collect()
+            iter.map(|&leaf| {
+                normalized_entry(leaf, normalizer).unwrap_or_else(|| self.process_leaf(leaf))
+            })
+            .collect()
         }
     }
 
@@ -134,6 +463,50 @@ impl TreeSitterProcessor {
                 .is_some_and(|x| !x.contains(node.kind()));
         !should_exclude
     }
+
+    /// Determine whether a node is in the scopes the user wants to process.
+    ///
+    /// Like [`should_include_node`](Self::should_include_node), exclusions take precedence and
+    /// the filters aren't applied if they aren't specified. Unlike the kind filters, these check
+    /// every ancestor of the node.
+    fn is_in_included_scope(&self, node: TSNode, text: &str) -> bool {
+        if self.exclude_scopes.is_none() && self.include_scopes.is_none() {
+            return true;
+        }
+        let ancestors: Vec<TSNode> = std::iter::successors(Some(node), TSNode::parent).collect();
+        let any_ancestor_matches = |scopes: &[Scope]| {
+            ancestors
+                .iter()
+                .any(|&ancestor| scopes.iter().any(|scope| scope.matches(ancestor, text)))
+        };
+        let should_exclude = self
+            .exclude_scopes
+            .as_deref()
+            .is_some_and(any_ancestor_matches)
+            || self
+                .include_scopes
+                .as_deref()
+                .is_some_and(|scopes| !any_ancestor_matches(scopes));
+        !should_exclude
+    }
+}
+
+/// Create an entry with the normalized text of a leaf, if any normalization rules apply to it.
+///
+/// Normalized text doesn't line up with the text of the node, so the entry spans the whole node
+/// rather than being split on graphemes.
+fn normalized_entry<'a>(
+    leaf: VectorLeaf<'a>,
+    normalizer: Option<&Normalizer>,
+) -> Option<Entry<'a>> {
+    let text = normalizer?.normalize(leaf.reference, leaf.text)?;
+    Some(Entry {
+        reference: leaf.reference,
+        text: Cow::from(text),
+        start_position: leaf.reference.start_position(),
+        end_position: leaf.reference.end_position(),
+        kind_id: leaf.reference.kind_id(),
+    })
 }
 
 /// Create a `DiffVector` from a `tree_sitter` tree
@@ -401,6 +774,13 @@ impl<'a> Hash for VectorLeaf<'a> {
     }
 }
 
+impl<'a> Hash for Entry<'a> {
+    fn hash<H: Hasher>(&self, state: &mut H) {
+        self.kind_id.hash(state);
+        self.text.hash(state);
+    }
+}
+
 impl<'a> PartialEq for Entry<'a> {
     fn eq(&self, other: &Entry) -> bool {
         self.kind_id == other.kind_id && self.text == other.text
@@ -508,6 +888,8 @@ mod tests {
 
     #[cfg(feature = "static-grammar-libs")]
     use crate::parse::generate_language;
+    #[cfg(feature = "static-grammar-libs")]
+    use test_case::test_case;
 
     #[test]
     fn test_should_filter_node() {
@@ -601,4 +983,161 @@ mod tests {
             assert_ne!(entries_a, entries_b);
         }
     }
+
+    #[cfg(feature = "static-grammar-libs")]
+    #[test_case("python", "f(a, b,)", "f(a, b)", true ; "trailing comma")]
+    #[test_case("python", "x = (a + b)", "x = a + b", true ; "redundant parens")]
+    #[test_case("python", "x = (a + b) * c", "x = a + b * c", false ; "significant parens")]
+    #[test_case("python", "x = 'a'", "x = \"a\"", true ; "quote style")]
+    #[test_case("python", "x = 1_000", "x = 0x3e8", true ; "numeric literal")]
+    #[test_case("python", "x = (a,)", "x = (a)", false ; "single element tuple")]
+    #[test_case("python", "x = (a, b,)", "x = (a, b)", true ; "tuple")]
+    #[test_case("python", "x[a,]", "x[a]", false ; "tuple subscript")]
+    #[test_case("typescript", "f(a, b,);", "f(a, b);", true ; "typescript trailing comma")]
+    #[test_case("typescript", "x = [a,,];", "x = [a,];", false ; "typescript array hole")]
+    #[test_case("typescript", "x = (a + b);", "x = a + b;", true ; "typescript redundant parens")]
+    #[test_case("typescript", "let x = (a, b);", "let x = a, b;", false ; "typescript comma operator")]
+    #[test_case("rust", "let x = (a + b);", "let x = a + b;", true ; "rust redundant parens")]
+    #[test_case("rust", "let x = (a,);", "let x = (a);", false ; "rust single element tuple")]
+    #[test_case("c", "int x = (a, b);", "int x = a, b;", false ; "c comma operator")]
+    fn test_normalization(language: &str, text_a: &str, text_b: &str, equivalent: bool) {
+        let ts_language = generate_language(language, &GrammarConfig::default()).unwrap();
+        let mut parser = Parser::new();
+        parser.set_language(&ts_language).unwrap();
+        let tree_a = parser.parse(text_a, None).unwrap();
+        let tree_b = parser.parse(text_b, None).unwrap();
+        let processor = TreeSitterProcessor {
+            normalization: NormalizationRules {
+                trailing_commas: true,
+                redundant_parens: true,
+                quote_style: true,
+                numeric_literals: true,
+            },
+            ..Default::default()
+        };
+        let entries_a = processor.process_with_language(&tree_a, text_a, language);
+        let entries_b = processor.process_with_language(&tree_b, text_b, language);
+        assert_eq!(entries_a == entries_b, equivalent);
+    }
+
+    #[cfg(feature = "static-grammar-libs")]
+    #[test_case("rust", "fn a() {}", "// Comment\nfn a() {}", true ; "rust line comment")]
+    #[test_case("rust", "fn a() {}", "/// Doc\nfn a() { /* b */ }", true ; "rust doc and block comments")]
+    #[test_case("python", "x = 1", "x = 1  # comment", true ; "python comment")]
+    #[test_case(
+        "python",
+        "def f():\n    return 1",
+        "def f():\n    \"\"\"Docstring.\"\"\"\n    return 1",
+        true ;
+        "python function docstring"
+    )]
+    #[test_case("python", "x = 1", "\"\"\"Module docstring.\"\"\"\nx = 1", true ; "python module docstring")]
+    #[test_case("python", "x = 1", "x = 1\n\"not a docstring\"", false ; "python string statement")]
+    #[test_case("python", "x = 1", "x = 2  # comment", false ; "python code change")]
+    fn test_ignore_comments(language: &str, text_a: &str, text_b: &str, equivalent: bool) {
+        let ts_language = generate_language(language, &GrammarConfig::default()).unwrap();
+        let mut parser = Parser::new();
+        parser.set_language(&ts_language).unwrap();
+        let tree_a = parser.parse(text_a, None).unwrap();
+        let tree_b = parser.parse(text_b, None).unwrap();
+        let processor = TreeSitterProcessor {
+            ignore_comments: true,
+            ..Default::default()
+        };
+        let entries_a = processor.process_with_language(&tree_a, text_a, language);
+        let entries_b = processor.process_with_language(&tree_b, text_b, language);
+        assert_eq!(entries_a == entries_b, equivalent);
+    }
+
+    #[test]
+    fn test_regions() {
+        let regions = Regions::new(vec![10..20, 0..5, 15..25, 4..6]);
+        assert_eq!(regions, Regions(vec![0..6, 10..25]));
+        assert!(regions.contains(&(0..6)));
+        assert!(regions.contains(&(12..25)));
+        assert!(!regions.contains(&(5..11)));
+        assert!(!regions.contains(&(24..26)));
+        assert!(!Regions::default().contains(&(0..1)));
+    }
+
+    #[cfg(feature = "static-grammar-libs")]
+    #[test_case(&[], &["(line_comment) @comment"], "fnfoo(){a}fnbar(){b}" ; "exclude")]
+    #[test_case(
+        &["(function_item name: (identifier) @_name (#eq? @_name \"bar\")) @function"],
+        &[],
+        "fnbar(){b}" ;
+        "include with predicate"
+    )]
+    #[test_case(
+        &["(function_item) @function"],
+        &["(line_comment) @comment", "(block) @block"],
+        "fnfoo()fnbar()" ;
+        "exclude takes precedence"
+    )]
+    fn test_queries(include: &[&str], exclude: &[&str], expected: &str) {
+        let text = "fn foo() { a }\n// a\nfn bar() { b }\n";
+        let language = generate_language("rust", &GrammarConfig::default()).unwrap();
+        let mut parser = Parser::new();
+        parser.set_language(&language).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let rules = QueryRules {
+            exclude: exclude.iter().map(ToString::to_string).collect(),
+            include: include.iter().map(ToString::to_string).collect(),
+        };
+        let processor = TreeSitterProcessor {
+            queries: HashMap::from([("rust".to_string(), rules)]),
+            ..Default::default()
+        };
+        processor.validate(&GrammarConfig::default()).unwrap();
+        let entries = processor.process_with_language(&tree, text, "rust");
+        let entries_text: String = entries.iter().map(|entry| entry.text.as_ref()).collect();
+        assert_eq!(entries_text, expected);
+    }
+
+    #[cfg(feature = "static-grammar-libs")]
+    #[test]
+    fn test_validate_invalid_query() {
+        let rules = QueryRules {
+            exclude: vec!["(not_a_node_kind) @capture".into()],
+            include: Vec::new(),
+        };
+        let processor = TreeSitterProcessor {
+            queries: HashMap::from([("rust".to_string(), rules)]),
+            ..Default::default()
+        };
+        let result = processor.validate(&GrammarConfig::default());
+        assert!(matches!(
+            result,
+            Err(QueryRulesError::InvalidQuery { language, .. }) if language == "rust"
+        ));
+    }
+
+    #[cfg(feature = "static-grammar-libs")]
+    #[test_case(Some("function_item"), None, None, "fnfoo(){a}fnbar(){b}" ; "kind")]
+    #[test_case(Some("function_item"), Some("foo"), None, "fnfoo(){a}" ; "kind and name")]
+    #[test_case(None, Some("Config"), None, "structConfig{c:u8}" ; "name")]
+    #[test_case(Some("function_item"), None, Some("bar"), "fnfoo(){a}" ; "exclusion")]
+    fn test_scopes(kind: Option<&str>, name: Option<&str>, excluded: Option<&str>, expected: &str) {
+        let text = "fn foo() { a }\nfn bar() { b }\nstruct Config { c: u8 }\n";
+        let language = generate_language("rust", &GrammarConfig::default()).unwrap();
+        let mut parser = Parser::new();
+        parser.set_language(&language).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let processor = TreeSitterProcessor {
+            include_scopes: Some(vec![Scope {
+                kind: kind.map(String::from),
+                name: name.map(String::from),
+            }]),
+            exclude_scopes: excluded.map(|name| {
+                vec![Scope {
+                    kind: None,
+                    name: Some(name.into()),
+                }]
+            }),
+            ..Default::default()
+        };
+        let entries = processor.process(&tree, text);
+        let entries_text: String = entries.iter().map(|entry| entry.text.as_ref()).collect();
+        assert_eq!(entries_text, expected);
+    }
 }
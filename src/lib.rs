@@ -7,22 +7,43 @@
 //!
 //! All of the methods used to create diffsitter are here and we have attempted to keep the library
 //! at least somewhat sane and organized for our own usage.
+//!
+//! If you want to diff documents from your own code, [`Differ`] is the stable entry point and
+//! returns typed [errors](differ::Error), unlike the lower level functions in this crate.
 
 pub mod cli;
+pub mod comments;
 pub mod config;
 pub mod console_utils;
 pub mod diff;
+pub mod diff_result;
+pub mod differ;
+pub mod dir_diff;
 mod figment_utils;
+pub mod git;
 pub mod input_processing;
 pub mod neg_idx_vec;
+pub mod normalization;
 pub mod parse;
 pub mod render;
+pub mod symbols;
+pub mod tree_diff;
+
+pub use diff_result::DiffResult;
+pub use differ::{DiffSession, Differ, Source};
 
 use anyhow::Result;
 use input_processing::VectorData;
 use log::{debug, info};
 use parse::GrammarConfig;
-use std::{fs, path::PathBuf};
+use std::{
+    fs,
+    io::{self, Read},
+    path::{Path, PathBuf},
+};
+
+/// The path that refers to stdin when it's given as an input
+pub const STDIN_PATH: &str = "-";
 
 /// Create an AST vector from a path
 ///
@@ -32,6 +53,10 @@ use std::{fs, path::PathBuf};
 /// `data` is used as an out-parameter. We need some external struct we can reference because the
 /// return type references the data in that struct.
 ///
+/// If the path is `-`, the text is read from stdin. The input is only read once, so this also
+/// works with pipes, like the ones created by process substitution. The language can't be deduced
+/// for inputs that don't have a file extension, so `file_type` must be set for those inputs.
+///
 /// This returns an anyhow [Result], which is bad practice for a library and will need to be
 /// refactored in the future. This method was originally used in the `diffsitter` binary so we
 /// didn't feel the need to specify a specific error type.
@@ -40,15 +65,46 @@ pub fn generate_ast_vector_data(
     file_type: Option<&str>,
     grammar_config: &GrammarConfig,
 ) -> Result<VectorData> {
-    let text = fs::read_to_string(&path)?;
     let file_name = path.to_string_lossy();
     debug!("Reading {file_name} to string");
+    let text = read_input(&path)?;
 
-    if let Some(file_type) = file_type {
+    let language = if let Some(file_type) = file_type {
         info!("Using user-set filetype \"{file_type}\" for {file_name}");
+        file_type
     } else {
         info!("Will deduce filetype from file extension");
+        parse::lang_name_from_path(&path, grammar_config)?
     };
-    let tree = parse::parse_file(&path, file_type, grammar_config)?;
-    Ok(VectorData { text, tree, path })
+    generate_ast_vector_data_from_text(&text, path.clone(), language, grammar_config)
+}
+
+/// Create an AST vector from text that's already in memory
+///
+/// This is useful for text that isn't saved to a file, like an unsaved buffer in an editor.
+/// `path` is the name that identifies the text, it doesn't have to exist. `language` is the name
+/// of the language to parse the text with, like the names from `diffsitter list`.
+pub fn generate_ast_vector_data_from_text(
+    text: &str,
+    path: PathBuf,
+    language: &str,
+    grammar_config: &GrammarConfig,
+) -> Result<VectorData> {
+    let tree = parse::parse_text(text, language, grammar_config)?;
+    Ok(VectorData {
+        text: text.to_string(),
+        tree,
+        path,
+    })
+}
+
+/// Read the text of an input, which can be either a file or stdin.
+fn read_input(path: &Path) -> io::Result<String> {
+    if path == Path::new(STDIN_PATH) {
+        let mut text = String::new();
+        io::stdin().read_to_string(&mut text)?;
+        Ok(text)
+    } else {
+        fs::read_to_string(path)
+    }
 }
@@ -0,0 +1,526 @@
+//! Normalization for syntax that code formatters commonly change.
+//!
+//! Formatters like rustfmt, black and clang-format add or remove trailing commas and redundant
+//! parentheses, switch the quotes around strings and rewrite numeric literals. None of these
+//! change what the code does, so these rules let us ignore them when diffing. If every rule that
+//! a formatter needs is enabled, reformatting a file produces an empty diff.
+
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node as TSNode;
+
+/// The normalization rules that can be enabled
+///
+/// Every rule is disabled by default.
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq, Eq, Default)]
+#[serde(rename_all = "kebab-case", default)]
+pub struct NormalizationRules {
+    /// Ignore commas that directly precede a closing bracket, like `f(a, b,)`
+    ///
+    /// The trailing comma in a tuple with a single element, like `(a,)`, is kept since it's
+    /// significant. Only the lists that we know about for a language are normalized.
+    pub trailing_commas: bool,
+
+    /// Ignore parentheses that don't change how an expression is parsed, like `return (a + b)`
+    ///
+    /// Only the places that we know about for a language are normalized, like the value of an
+    /// assignment.
+    pub redundant_parens: bool,
+
+    /// Treat single and double quotes around strings as equivalent, like `'a'` and `"a"`
+    pub quote_style: bool,
+
+    /// Compare numeric literals by their value, like `1_000`, `1000` and `0x3e8`
+    pub numeric_literals: bool,
+}
+
+impl NormalizationRules {
+    /// Whether any of the rules are enabled
+    #[must_use]
+    pub fn any_enabled(&self) -> bool {
+        self.trailing_commas || self.redundant_parens || self.quote_style || self.numeric_literals
+    }
+}
+
+/// The node kinds that the normalization rules need to know about for a language
+///
+/// The rules for commas and parentheses only apply where a language lists them explicitly, since
+/// removing either of them in the wrong place can change what the code means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct LanguageSyntax {
+    /// Kinds of string literals that can be quoted with either single or double quotes
+    strings: &'static [&'static str],
+    /// Kinds of numeric literals
+    numbers: &'static [&'static str],
+    /// Kinds of lists where a trailing comma can be removed, like argument lists
+    lists: &'static [&'static str],
+    /// Kinds of tuples, where the trailing comma can only be removed if there's more than one
+    /// element
+    tuples: &'static [&'static str],
+    /// The places where parentheses around an expression are redundant, as the kind of the
+    /// parent node and the field the expression is in. A field of `None` matches any child.
+    paren_parents: &'static [(&'static str, Option<&'static str>)],
+    /// Kinds of expressions that need their parentheses even where they would otherwise be
+    /// redundant, like JavaScript's comma operator in `let x = (a, b)`
+    paren_required: &'static [&'static str],
+}
+
+impl LanguageSyntax {
+    /// Get the syntax for a language, falling back to common literal kinds for languages that we
+    /// don't have specific rules for.
+    fn new(language: &str) -> Self {
+        let fallback = LanguageSyntax {
+            strings: &["string"],
+            numbers: &[
+                "number",
+                "number_literal",
+                "integer",
+                "integer_literal",
+                "float",
+                "float_literal",
+            ],
+            lists: &[],
+            tuples: &[],
+            paren_parents: &[],
+            paren_required: &[],
+        };
+        match language {
+            "rust" => LanguageSyntax {
+                // Rust strings can only use double quotes
+                strings: &[],
+                numbers: &["integer_literal", "float_literal"],
+                lists: &[
+                    "arguments",
+                    "parameters",
+                    "array_expression",
+                    "field_initializer_list",
+                    "field_declaration_list",
+                    "ordered_field_declaration_list",
+                    "enum_variant_list",
+                    "use_list",
+                    "type_arguments",
+                    "type_parameters",
+                    "struct_pattern",
+                    "tuple_struct_pattern",
+                    "slice_pattern",
+                ],
+                tuples: &["tuple_expression", "tuple_pattern", "tuple_type"],
+                paren_parents: &[
+                    ("let_declaration", Some("value")),
+                    ("assignment_expression", Some("right")),
+                    ("compound_assignment_expr", Some("right")),
+                    ("return_expression", None),
+                    ("arguments", None),
+                    ("array_expression", None),
+                    ("field_initializer", Some("value")),
+                    ("parenthesized_expression", None),
+                ],
+                paren_required: &[],
+            },
+            "python" => LanguageSyntax {
+                strings: &["string"],
+                numbers: &["integer", "float"],
+                // A trailing comma in a subscript makes a tuple, so `x[a,]` isn't `x[a]`
+                lists: &[
+                    "argument_list",
+                    "parameters",
+                    "list",
+                    "set",
+                    "dictionary",
+                    "list_pattern",
+                ],
+                tuples: &["tuple", "tuple_pattern"],
+                paren_parents: &[
+                    ("assignment", Some("right")),
+                    ("augmented_assignment", Some("right")),
+                    ("return_statement", None),
+                    ("expression_statement", None),
+                    ("argument_list", None),
+                    ("keyword_argument", Some("value")),
+                    ("parenthesized_expression", None),
+                ],
+                // An assignment expression that's used as a statement has to be parenthesized
+                paren_required: &["named_expression"],
+            },
+            "go" => LanguageSyntax {
+                // Go's single quotes are for runes and backticks are for raw strings
+                strings: &[],
+                numbers: &["int_literal", "float_literal", "imaginary_literal"],
+                lists: &["argument_list", "parameter_list", "literal_value"],
+                tuples: &[],
+                paren_parents: &[
+                    ("expression_list", None),
+                    ("argument_list", None),
+                    ("parenthesized_expression", None),
+                ],
+                paren_required: &[],
+            },
+            // Statements and calls aren't listed, since the parentheses in `({a} = b)` and
+            // `(function() {})()` keep the statement from being parsed as a block or a declaration
+            "typescript" | "tsx" => LanguageSyntax {
+                strings: &["string"],
+                numbers: &["number"],
+                lists: &[
+                    "arguments",
+                    "formal_parameters",
+                    "array",
+                    "object",
+                    "array_pattern",
+                    "object_pattern",
+                    "named_imports",
+                    "export_clause",
+                    "enum_body",
+                ],
+                tuples: &[],
+                paren_parents: &[
+                    ("variable_declarator", Some("value")),
+                    ("assignment_expression", Some("right")),
+                    ("return_statement", None),
+                    ("arguments", None),
+                    ("array", None),
+                    ("pair", Some("value")),
+                    ("parenthesized_expression", None),
+                ],
+                paren_required: &["sequence_expression"],
+            },
+            // Macros can stringify their arguments, so the parentheses in arguments are kept
+            "c" => LanguageSyntax {
+                // C uses single quotes for characters
+                strings: &[],
+                numbers: &["number_literal"],
+                lists: &["initializer_list", "enumerator_list"],
+                tuples: &[],
+                paren_parents: &[
+                    ("init_declarator", Some("value")),
+                    ("assignment_expression", Some("right")),
+                    ("return_statement", None),
+                    ("parenthesized_expression", None),
+                ],
+                paren_required: &["comma_expression"],
+            },
+            // `return (x)` can return a reference in C++, if the function's return type is
+            // `decltype(auto)`
+            "cpp" => LanguageSyntax {
+                strings: &[],
+                numbers: &["number_literal"],
+                lists: &["initializer_list", "enumerator_list"],
+                tuples: &[],
+                paren_parents: &[
+                    ("init_declarator", Some("value")),
+                    ("assignment_expression", Some("right")),
+                    ("parenthesized_expression", None),
+                ],
+                paren_required: &["comma_expression"],
+            },
+            "java" => LanguageSyntax {
+                lists: &[
+                    "array_initializer",
+                    "element_value_array_initializer",
+                    "enum_body",
+                ],
+                paren_parents: &[
+                    ("variable_declarator", Some("value")),
+                    ("assignment_expression", Some("right")),
+                    ("return_statement", None),
+                    ("argument_list", None),
+                    ("parenthesized_expression", None),
+                ],
+                ..fallback
+            },
+            "c_sharp" => LanguageSyntax {
+                lists: &["initializer_expression", "enum_member_declaration_list"],
+                ..fallback
+            },
+            "ruby" => LanguageSyntax {
+                lists: &["argument_list", "array", "hash"],
+                ..fallback
+            },
+            "php" => LanguageSyntax {
+                lists: &[
+                    "arguments",
+                    "formal_parameters",
+                    "array_creation_expression",
+                ],
+                ..fallback
+            },
+            _ => fallback,
+        }
+    }
+}
+
+/// Applies normalization rules to the leaves of a syntax tree for a particular language
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Normalizer {
+    rules: NormalizationRules,
+    syntax: LanguageSyntax,
+}
+
+impl Normalizer {
+    #[must_use]
+    pub fn new(rules: NormalizationRules, language: &str) -> Self {
+        Normalizer {
+            rules,
+            syntax: LanguageSyntax::new(language),
+        }
+    }
+
+    /// Whether a leaf is syntax that should be ignored, like a trailing comma or a redundant
+    /// parenthesis.
+    #[must_use]
+    pub fn is_ignored(&self, leaf: TSNode) -> bool {
+        match leaf.kind() {
+            "," => self.rules.trailing_commas && self.is_trailing_comma(leaf),
+            "(" | ")" => {
+                self.rules.redundant_parens
+                    && leaf
+                        .parent()
+                        .is_some_and(|parent| self.has_redundant_parens(parent))
+            }
+            _ => false,
+        }
+    }
+
+    /// Get the normalized text for a leaf, if any of the rules apply to it.
+    ///
+    /// Normalized leaves should be compared as a whole rather than being split into graphemes,
+    /// because the normalized text doesn't line up with the original text.
+    #[must_use]
+    pub fn normalize(&self, leaf: TSNode, text: &str) -> Option<String> {
+        if self.rules.numeric_literals && self.syntax.numbers.contains(&leaf.kind()) {
+            return Some(normalize_number(text));
+        }
+        if self.rules.quote_style && self.is_string_delimiter(leaf) {
+            return Some(text.replace('\'', "\""));
+        }
+        None
+    }
+
+    fn is_trailing_comma(&self, comma: TSNode) -> bool {
+        let Some(list) = comma.parent() else {
+            return false;
+        };
+        let is_tuple = self.syntax.tuples.contains(&list.kind());
+        if !is_tuple && !self.syntax.lists.contains(&list.kind()) {
+            return false;
+        }
+        let next = sibling_skipping_extras(comma, TSNode::next_sibling);
+        if !next.is_some_and(|node| matches!(node.kind(), ")" | "]" | "}")) {
+            return false;
+        }
+        // A comma that follows another comma is a hole, like the one in JavaScript's `[a,,]`
+        let prev = sibling_skipping_extras(comma, TSNode::prev_sibling);
+        if !prev.is_some_and(|node| node.is_named()) {
+            return false;
+        }
+        // `(a,)` is a tuple but `(a)` isn't
+        !is_tuple || elements(list).len() > 1
+    }
+
+    /// Whether the parentheses of an expression can be removed without changing how the code is
+    /// parsed.
+    ///
+    /// Parentheses are only redundant in the places that the language's syntax lists, like the
+    /// value of an assignment, and only if they contain a single expression.
+    fn has_redundant_parens(&self, expr: TSNode) -> bool {
+        if expr.kind() != "parenthesized_expression" {
+            return false;
+        }
+        let [inner] = elements(expr)[..] else {
+            return false;
+        };
+        let Some(parent) = expr.parent() else {
+            return false;
+        };
+        let in_redundant_place = self.syntax.paren_parents.iter().any(|(kind, field)| {
+            parent.kind() == *kind
+                && field.is_none_or(|field| parent.child_by_field_name(field) == Some(expr))
+        });
+        in_redundant_place && !self.syntax.paren_required.contains(&inner.kind())
+    }
+
+    /// Whether a leaf is the opening or closing delimiter of a string, like `"` or `f'`.
+    fn is_string_delimiter(&self, leaf: TSNode) -> bool {
+        let Some(parent) = leaf.parent() else {
+            return false;
+        };
+        if !self.syntax.strings.contains(&parent.kind()) {
+            return false;
+        }
+        let is_first_or_last = leaf.prev_sibling().is_none() || leaf.next_sibling().is_none();
+        is_first_or_last && leaf.kind() != "string_content"
+    }
+}
+
+/// Get the nearest sibling of a node in a direction that isn't an extra, like a comment.
+fn sibling_skipping_extras<'a>(
+    node: TSNode<'a>,
+    sibling: impl Fn(&TSNode<'a>) -> Option<TSNode<'a>>,
+) -> Option<TSNode<'a>> {
+    let mut next = sibling(&node);
+    while let Some(node) = next.filter(TSNode::is_extra) {
+        next = sibling(&node);
+    }
+    next
+}
+
+/// Get the named children of a node that aren't extras, like the elements of a list without the
+/// comments between them.
+fn elements(node: TSNode) -> Vec<TSNode> {
+    let mut cursor = node.walk();
+    node.named_children(&mut cursor)
+        .filter(|child| !child.is_extra())
+        .collect()
+}
+
+/// Convert a numeric literal to a canonical form.
+///
+/// Digit separators are removed, integers in other bases are converted to decimal, and floats are
+/// written in scientific notation. Type suffixes (like `u8` in Rust) are kept. If the literal
+/// can't be parsed, only the separators are removed and the literal is lowercased.
+fn normalize_number(text: &str) -> String {
+    let text = text.replace('_', "").to_ascii_lowercase();
+    let radix_prefix = [("0x", 16), ("0o", 8), ("0b", 2)]
+        .into_iter()
+        .find(|(prefix, _)| text.starts_with(prefix));
+
+    let (value, suffix) = if let Some((prefix, radix)) = radix_prefix {
+        let digits = &text[prefix.len()..];
+        let end = digits
+            .find(|c: char| !c.is_digit(radix))
+            .unwrap_or(digits.len());
+        let value = u128::from_str_radix(&digits[..end], radix)
+            .ok()
+            .map(|value| value.to_string());
+        (value, &digits[end..])
+    } else {
+        let end = decimal_len(&text);
+        let number = &text[..end];
+        let value = if number.contains(['.', 'e']) {
+            number
+                .parse::<f64>()
+                .ok()
+                .filter(|value| value.is_finite())
+                .map(|value| format!("{value:e}"))
+        } else if number.len() > 1 && number.starts_with('0') {
+            // A leading zero denotes an octal literal in some languages, like C
+            None
+        } else {
+            number.parse::<u128>().ok().map(|value| value.to_string())
+        };
+        (value, &text[end..])
+    };
+
+    match value {
+        Some(value) => format!("{value}{suffix}"),
+        None => text,
+    }
+}
+
+/// The length of the decimal number at the start of a lowercase numeric literal, including the
+/// fractional part and exponent.
+fn decimal_len(text: &str) -> usize {
+    let bytes = text.as_bytes();
+    let mut len = bytes
+        .iter()
+        .take_while(|b| b.is_ascii_digit() || **b == b'.')
+        .count();
+
+    if bytes.get(len) == Some(&b'e') {
+        let sign_len = usize::from(matches!(bytes.get(len + 1), Some(b'+' | b'-')));
+        let exponent_len = bytes[len + 1 + sign_len..]
+            .iter()
+            .take_while(|b| b.is_ascii_digit())
+            .count();
+        if exponent_len > 0 {
+            len += 1 + sign_len + exponent_len;
+        }
+    }
+    len
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use test_case::test_case;
+
+    #[cfg(feature = "static-grammar-libs")]
+    use crate::parse::{generate_language, GrammarConfig};
+
+    #[test_case("1000", "1000" ; "plain integer")]
+    #[test_case("1_000", "1000" ; "digit separators")]
+    #[test_case("0x3E8", "1000" ; "hex")]
+    #[test_case("0o1750", "1000" ; "octal")]
+    #[test_case("0b1111101000", "1000" ; "binary")]
+    #[test_case("1000u32", "1000u32" ; "integer suffix")]
+    #[test_case("0xffu8", "255u8" ; "hex suffix")]
+    #[test_case("1.0", "1e0" ; "float")]
+    #[test_case("1.", "1e0" ; "float without fraction")]
+    #[test_case("1E3", "1e3" ; "exponent")]
+    #[test_case("1000.0", "1e3" ; "float with trailing zeros")]
+    #[test_case("2.5e-3f64", "2.5e-3f64" ; "float suffix")]
+    #[test_case("010", "010" ; "leading zero")]
+    #[test_case("0", "0" ; "zero")]
+    #[test_case("10j", "10j" ; "imaginary")]
+    fn test_normalize_number(text: &str, expected: &str) {
+        assert_eq!(normalize_number(text), expected);
+    }
+
+    #[test_case("123", 3)]
+    #[test_case("1.5e10f32", 6)]
+    #[test_case("1e", 1)]
+    #[test_case("2e+5", 4)]
+    fn test_decimal_len(text: &str, expected: usize) {
+        assert_eq!(decimal_len(text), expected);
+    }
+
+    #[test]
+    fn test_any_enabled() {
+        assert!(!NormalizationRules::default().any_enabled());
+        let rules = NormalizationRules {
+            quote_style: true,
+            ..NormalizationRules::default()
+        };
+        assert!(rules.any_enabled());
+    }
+
+    // NOTE: this test has to be gated behind the 'static-grammar-libs' cargo feature, otherwise the
+    // grammars aren't available to parse the documents.
+    #[cfg(feature = "static-grammar-libs")]
+    #[test_case("python", "x = (a,  # b\n)" ; "single element tuple with comment")]
+    #[test_case("python", "x[a,]" ; "tuple subscript")]
+    #[test_case("typescript", "({a} = b);" ; "destructuring assignment statement")]
+    #[test_case("typescript", "(function() {})();" ; "called function expression")]
+    #[test_case("typescript", "let x = (a, b);" ; "comma operator")]
+    #[test_case("cpp", "decltype(auto) f() { return (x); }" ; "cpp returned reference")]
+    fn test_significant_syntax_is_kept(language: &str, text: &str) {
+        let ts_language = generate_language(language, &GrammarConfig::default()).unwrap();
+        let mut parser = tree_sitter::Parser::new();
+        parser.set_language(&ts_language).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let rules = NormalizationRules {
+            trailing_commas: true,
+            redundant_parens: true,
+            quote_style: true,
+            numeric_literals: true,
+        };
+        let normalizer = Normalizer::new(rules, language);
+
+        let mut cursor = tree.walk();
+        let mut ignored = Vec::new();
+        'walk: loop {
+            let node = cursor.node();
+            if node.child_count() == 0 && normalizer.is_ignored(node) {
+                ignored.push(node.kind());
+            }
+            if cursor.goto_first_child() || cursor.goto_next_sibling() {
+                continue;
+            }
+            while cursor.goto_parent() {
+                if cursor.goto_next_sibling() {
+                    continue 'walk;
+                }
+            }
+            break;
+        }
+        assert!(ignored.is_empty(), "ignored {ignored:?}");
+    }
+}
@@ -96,6 +96,9 @@ pub enum LoadingError {
     #[error("could not parse {0} with tree-sitter")]
     TSParseFailure(PathBuf),
 
+    #[error("could not parse text as {0} with tree-sitter")]
+    TSTextParseFailure(String),
+
     #[error("Some IO error was encountered")]
     IoError(#[from] io::Error),
 
@@ -416,6 +419,23 @@ pub fn ts_language_abi_checked(ts_language: &Language) -> Result<(), LoadingErro
     Ok(())
 }
 
+/// Get the name of the language to use for a path, based on its file extension.
+///
+/// # Errors
+///
+/// This returns a [`NoFileExt`](LoadingError::NoFileExt) error if the path doesn't have an
+/// extension, or an [`UnsupportedExt`](LoadingError::UnsupportedExt) error if the extension isn't
+/// mapped to a language.
+pub fn lang_name_from_path<'cfg>(
+    p: &Path,
+    config: &'cfg GrammarConfig,
+) -> Result<&'cfg str, LoadingError> {
+    match p.extension() {
+        Some(ext) => lang_name_from_file_ext(&ext.to_string_lossy(), config),
+        None => Err(LoadingError::NoFileExt(p.to_string_lossy().to_string())),
+    }
+}
+
 /// Parse a file to an AST
 ///
 /// The user may optionally supply the language to use. If the language is not supplied, it will be
@@ -429,18 +449,10 @@ pub fn parse_file(
     // Either use the provided language or infer the language to use with the parser from the file
     // extension
     let resolved_language = match language {
-        Some(lang) => Ok(lang),
-        None => {
-            if let Some(ext) = p.extension() {
-                lang_name_from_file_ext(&ext.to_string_lossy(), config)
-            } else {
-                Err(LoadingError::NoFileExt(p.to_string_lossy().to_string()))
-            }
-        }
-    }?;
-    let mut parser = Parser::new();
-    let ts_lang = generate_language(resolved_language, config)?;
-    parser.set_language(&ts_lang)?;
+        Some(lang) => lang,
+        None => lang_name_from_path(p, config)?,
+    };
+    let mut parser = parser_for_language(resolved_language, config)?;
     let text = fs::read_to_string(p)?;
     match parser.parse(&text, None) {
         Some(ast) => {
@@ -451,6 +463,34 @@ pub fn parse_file(
     }
 }
 
+/// Parse text to an AST with the given language
+///
+/// This is useful for text that doesn't come from a file on disk, like a blob from a git
+/// repository.
+#[time("info", "parse::{}")]
+pub fn parse_text(
+    text: &str,
+    language: &str,
+    config: &GrammarConfig,
+) -> Result<Tree, LoadingError> {
+    let mut parser = parser_for_language(language, config)?;
+    match parser.parse(text, None) {
+        Some(ast) => {
+            debug!("Parsed AST");
+            Ok(ast)
+        }
+        None => Err(LoadingError::TSTextParseFailure(language.to_string())),
+    }
+}
+
+/// Create a tree-sitter parser for a language
+fn parser_for_language(language: &str, config: &GrammarConfig) -> Result<Parser, LoadingError> {
+    let mut parser = Parser::new();
+    let ts_lang = generate_language(language, config)?;
+    parser.set_language(&ts_lang)?;
+    Ok(parser)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,215 @@
+use crate::diff::{Line, RichHunk};
+use crate::render::{unified::move_notes, DisplayData};
+use serde::{Deserialize, Serialize};
+
+/// The severity that annotations are reported with
+///
+/// Each format has its own names for these levels, which the renderers map them to.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum AnnotationLevel {
+    /// Informational, which doesn't fail a check
+    #[default]
+    Notice,
+    /// A warning, which some tools display more prominently
+    Warning,
+    /// An error, which usually fails a check
+    Error,
+}
+
+/// Whether an annotation is for added or deleted code
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub enum ChangeKind {
+    Addition,
+    Deletion,
+}
+
+impl ChangeKind {
+    /// A stable identifier for the kind of change, which tools can use to filter annotations
+    pub fn id(self) -> &'static str {
+        match self {
+            ChangeKind::Addition => "addition",
+            ChangeKind::Deletion => "deletion",
+        }
+    }
+}
+
+/// A position in a document, as it's displayed in editors and CI tools
+#[derive(Debug, Eq, PartialEq, Clone, Copy)]
+pub struct Location {
+    /// The line, starting from 1
+    pub line: usize,
+    /// The column in characters, starting from 1
+    pub column: usize,
+}
+
+/// A message that's attached to the range of a document that a hunk covers
+#[derive(Debug, Eq, PartialEq, Clone)]
+pub struct Annotation<'a> {
+    /// The path of the document the hunk is from
+    pub path: &'a str,
+    /// Whether the hunk has added or deleted code
+    pub kind: ChangeKind,
+    /// The start of the first edited entry in the hunk
+    pub start: Location,
+    /// The position right after the last edited entry in the hunk, so the range is exclusive
+    pub end: Location,
+    /// A description of the change, like `Added code in main`
+    pub message: String,
+}
+
+/// Create an annotation for each hunk in the diff.
+///
+/// Annotations are in the same order as the hunk groups, so each deletion comes right before the
+/// addition that replaced it.
+pub fn annotations<'a>(data: &DisplayData<'a>) -> Vec<Annotation<'a>> {
+    let old_lines: Vec<_> = data.old.text.lines().collect();
+    let new_lines: Vec<_> = data.new.text.lines().collect();
+    let mut annotations = Vec::new();
+
+    for hunk_idx in data.hunk_groups().concat() {
+        let (kind, lines, path) = match &data.hunks.0[hunk_idx] {
+            RichHunk::Old(_) => (ChangeKind::Deletion, &old_lines, data.old.filename),
+            RichHunk::New(_) => (ChangeKind::Addition, &new_lines, data.new.filename),
+        };
+        let hunk = data.hunks.0[hunk_idx].as_ref();
+        let (Some(first_line), Some(last_line)) = (hunk.0.first(), hunk.0.last()) else {
+            continue;
+        };
+
+        let mut message = match kind {
+            ChangeKind::Addition => "Added code".to_string(),
+            ChangeKind::Deletion => "Deleted code".to_string(),
+        };
+        if let Some(symbol) = data
+            .symbols
+            .get(hunk_idx)
+            .and_then(|symbols| symbols.last())
+        {
+            message.push_str(&format!(" in {}", symbol.name));
+        }
+        let notes: Vec<_> = move_notes(&data.moves, hunk_idx, 1).collect();
+        if !notes.is_empty() {
+            message.push_str(&format!(" ({})", notes.join(", ")));
+        }
+
+        annotations.push(Annotation {
+            path,
+            kind,
+            start: start_location(first_line, lines),
+            end: end_location(last_line, lines),
+            message,
+        });
+    }
+    annotations
+}
+
+/// Get the location where the edits on the first line of a hunk start.
+///
+/// If the line doesn't have an entry that starts on it, the whole line is considered edited.
+pub fn start_location(line: &Line, lines: &[&str]) -> Location {
+    let column = line
+        .entries
+        .first()
+        .map(|entry| entry.start_position())
+        .filter(|start| start.row == line.line_index)
+        .map_or(0, |start| start.column);
+    location(line.line_index, column, lines)
+}
+
+/// Get the location right after the edits on the last line of a hunk.
+///
+/// Entries can span multiple lines, in which case the location is on the line where the last
+/// entry ends. If the line doesn't have any entries, the whole line is considered edited.
+fn end_location(line: &Line, lines: &[&str]) -> Location {
+    let line_len = |row: usize| lines.get(row).map_or(0, |text| text.len());
+    match line.entries.last().map(|entry| entry.end_position()) {
+        Some(end) if end.row >= line.line_index => location(end.row, end.column, lines),
+        _ => location(line.line_index, line_len(line.line_index), lines),
+    }
+}
+
+/// Convert a row and a byte offset in that row to a 1-based line and character column.
+fn location(row: usize, byte_column: usize, lines: &[&str]) -> Location {
+    let text = lines.get(row).copied().unwrap_or_default();
+    let mut end = byte_column.min(text.len());
+    while !text.is_char_boundary(end) {
+        end -= 1;
+    }
+    Location {
+        line: row + 1,
+        column: text[..end].chars().count() + 1,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{Hunk, Move, RichHunks};
+    use crate::diff_result::Position;
+    use crate::render::DocumentDiffData;
+    use crate::symbols::Symbol;
+    use pretty_assertions::assert_eq;
+    use test_case::test_case;
+
+    #[test_case(0, 0, Location { line: 1, column: 1 } ; "start of line")]
+    #[test_case(1, 4, Location { line: 2, column: 5 } ; "ascii")]
+    #[test_case(0, 6, Location { line: 1, column: 6 } ; "multibyte characters")]
+    #[test_case(0, 5, Location { line: 1, column: 5 } ; "inside of a character")]
+    #[test_case(5, 3, Location { line: 6, column: 1 } ; "missing line")]
+    fn test_location(row: usize, byte_column: usize, expected: Location) {
+        let lines = ["let é = \"ü\";", "    a();"];
+        assert_eq!(location(row, byte_column, &lines), expected);
+    }
+
+    #[test]
+    fn hunk_annotations() {
+        let data = DisplayData {
+            hunks: RichHunks(vec![
+                RichHunk::New(Hunk(vec![Line::new(1)])),
+                RichHunk::Old(Hunk(vec![Line::new(1), Line::new(2)])),
+            ]),
+            old: DocumentDiffData {
+                filename: "a.rs",
+                text: "fn main() {\n    a();\n    b();\n}\n",
+                language: "rust",
+            },
+            new: DocumentDiffData {
+                filename: "b.rs",
+                text: "fn main() {\n    c();\n}\n",
+                language: "rust",
+            },
+            moves: vec![Move {
+                old_hunk: 1,
+                new_hunk: 0,
+                source: Position { row: 1, column: 4 }..Position { row: 1, column: 8 },
+                destination: Position { row: 1, column: 4 }..Position { row: 1, column: 8 },
+            }],
+            symbols: vec![
+                vec![Symbol {
+                    kind: "function_item".into(),
+                    name: "main".into(),
+                }],
+                Vec::new(),
+            ],
+            groups: vec![vec![1, 0]],
+        };
+        let expected = vec![
+            Annotation {
+                path: "a.rs",
+                kind: ChangeKind::Deletion,
+                start: Location { line: 2, column: 1 },
+                end: Location { line: 3, column: 9 },
+                message: "Deleted code (moved to 2)".into(),
+            },
+            Annotation {
+                path: "b.rs",
+                kind: ChangeKind::Addition,
+                start: Location { line: 2, column: 1 },
+                end: Location { line: 2, column: 9 },
+                message: "Added code in main (moved from 2)".into(),
+            },
+        ];
+        assert_eq!(annotations(&data), expected);
+    }
+}
@@ -0,0 +1,166 @@
+use crate::render::{
+    annotations::{annotations, Annotation, AnnotationLevel},
+    html::escape_html,
+    DisplayData, RenderState, Renderer,
+};
+use anyhow::Result;
+use console::Term;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// A renderer that writes Checkstyle XML, which most CI systems and code review tools can import
+/// as lint results.
+///
+/// Each hunk is reported as an `<error>` element at the start of its edited entries. Every diff
+/// is written into a single `<checkstyle>` document, so the output of a directory diff is still
+/// one document.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct Checkstyle {
+    /// The severity of the reported changes
+    pub level: AnnotationLevel,
+}
+
+impl Renderer for Checkstyle {
+    fn render(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        term_info: Option<&Term>,
+    ) -> Result<()> {
+        // On its own, a diff is written as a complete document
+        let mut state = RenderState::default();
+        self.begin(writer, &mut state)?;
+        self.render_part(writer, data, term_info, &mut state)?;
+        self.end(writer, &mut state)
+    }
+
+    fn render_part(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        _term_info: Option<&Term>,
+        _state: &mut RenderState,
+    ) -> Result<()> {
+        let severity = match self.level {
+            AnnotationLevel::Notice => "info",
+            AnnotationLevel::Warning => "warning",
+            AnnotationLevel::Error => "error",
+        };
+        // Each file gets one element, even if its hunks are interleaved with the hunks of the
+        // other document
+        let annotations = annotations(data);
+        let mut paths: Vec<&str> = Vec::new();
+        for annotation in &annotations {
+            if !paths.contains(&annotation.path) {
+                paths.push(annotation.path);
+            }
+        }
+        for path in paths {
+            writeln!(writer, "  <file name=\"{}\">", escape_html(path))?;
+            for annotation in annotations.iter().filter(|a| a.path == path) {
+                let Annotation {
+                    kind,
+                    start,
+                    message,
+                    ..
+                } = annotation;
+                writeln!(
+                    writer,
+                    "    <error line=\"{}\" column=\"{}\" severity=\"{severity}\" message=\"{}\" source=\"diffsitter.{}\"/>",
+                    start.line,
+                    start.column,
+                    escape_html(message),
+                    kind.id()
+                )?;
+            }
+            writeln!(writer, "  </file>")?;
+        }
+        Ok(())
+    }
+
+    fn begin(&self, writer: &mut dyn Write, _state: &mut RenderState) -> Result<()> {
+        writeln!(writer, "<?xml version=\"1.0\" encoding=\"UTF-8\"?>")?;
+        writeln!(writer, "<checkstyle version=\"4.3\">")?;
+        Ok(())
+    }
+
+    fn end(&self, writer: &mut dyn Write, _state: &mut RenderState) -> Result<()> {
+        writeln!(writer, "</checkstyle>")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{Hunk, Line, RichHunk, RichHunks};
+    use crate::render::DocumentDiffData;
+    use pretty_assertions::assert_eq;
+
+    fn test_data() -> DisplayData<'static> {
+        DisplayData {
+            hunks: RichHunks(vec![
+                RichHunk::Old(Hunk(vec![Line::new(1)])),
+                RichHunk::New(Hunk(vec![Line::new(1)])),
+                RichHunk::New(Hunk(vec![Line::new(3)])),
+            ]),
+            old: DocumentDiffData {
+                filename: "a.rs",
+                text: "fn main() {\n    a();\n}\n",
+                language: "rust",
+            },
+            new: DocumentDiffData {
+                filename: "<b>.rs",
+                text: "fn main() {\n    b();\n}\nfn c() {}\n",
+                language: "rust",
+            },
+            moves: Vec::new(),
+            symbols: Vec::new(),
+            groups: vec![vec![0, 1], vec![2]],
+        }
+    }
+
+    const EXPECTED_FILES: &str = r#"  <file name="a.rs">
+    <error line="2" column="1" severity="info" message="Deleted code" source="diffsitter.deletion"/>
+  </file>
+  <file name="&lt;b&gt;.rs">
+    <error line="2" column="1" severity="info" message="Added code" source="diffsitter.addition"/>
+    <error line="4" column="1" severity="info" message="Added code" source="diffsitter.addition"/>
+  </file>
+"#;
+
+    #[test]
+    fn render_document() {
+        let data = test_data();
+        let renderer = Checkstyle::default();
+        let mut output = Vec::new();
+        let mut state = RenderState::default();
+        renderer.begin(&mut output, &mut state).unwrap();
+        // Rendering two diffs still writes a single document
+        renderer
+            .render_part(&mut output, &data, None, &mut state)
+            .unwrap();
+        renderer
+            .render_part(&mut output, &data, None, &mut state)
+            .unwrap();
+        renderer.end(&mut output, &mut state).unwrap();
+        let expected = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n{EXPECTED_FILES}{EXPECTED_FILES}</checkstyle>\n"
+        );
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+
+    /// Rendering a single diff on its own writes a complete document.
+    #[test]
+    fn render_standalone_document() {
+        let mut output = Vec::new();
+        Checkstyle::default()
+            .render(&mut output, &test_data(), None)
+            .unwrap();
+        let expected = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<checkstyle version=\"4.3\">\n{EXPECTED_FILES}</checkstyle>\n"
+        );
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
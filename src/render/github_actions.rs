@@ -0,0 +1,128 @@
+use crate::render::{
+    annotations::{annotations, AnnotationLevel},
+    default_option, DisplayData, Renderer,
+};
+use anyhow::Result;
+use console::Term;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// A renderer that writes GitHub Actions workflow commands, which GitHub displays as annotations
+/// on the lines of a pull request.
+///
+/// Each hunk is written as a `::notice`, `::warning` or `::error` command with the location of
+/// the edited entries, like `::notice file=a.rs,line=2,endLine=2,col=5,endColumn=8::Added code`.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct GithubActions {
+    /// The level of the annotations
+    pub level: AnnotationLevel,
+    /// The title of the annotations, which GitHub displays above the message
+    #[serde(default = "default_option")]
+    pub title: Option<String>,
+}
+
+impl Renderer for GithubActions {
+    fn render(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        _term_info: Option<&Term>,
+    ) -> Result<()> {
+        let command = match self.level {
+            AnnotationLevel::Notice => "notice",
+            AnnotationLevel::Warning => "warning",
+            AnnotationLevel::Error => "error",
+        };
+        for annotation in annotations(data) {
+            let (start, end) = (annotation.start, annotation.end);
+            let mut properties = vec![
+                format!("file={}", escape_property(annotation.path)),
+                format!("line={}", start.line),
+                format!("endLine={}", end.line),
+                format!("col={}", start.column),
+            ];
+            // GitHub's end column is inclusive, unlike the end of the annotation
+            properties.push(format!("endColumn={}", (end.column - 1).max(start.column)));
+            if let Some(title) = &self.title {
+                properties.push(format!("title={}", escape_property(title)));
+            }
+            writeln!(
+                writer,
+                "::{command} {}::{}",
+                properties.join(","),
+                escape_data(&annotation.message)
+            )?;
+        }
+        Ok(())
+    }
+}
+
+/// Escape the message of a workflow command, so it stays on one line.
+fn escape_data(text: &str) -> String {
+    text.replace('%', "%25")
+        .replace('\r', "%0D")
+        .replace('\n', "%0A")
+}
+
+/// Escape the value of a workflow command property, which also can't contain the separators
+/// between properties.
+fn escape_property(text: &str) -> String {
+    escape_data(text).replace(':', "%3A").replace(',', "%2C")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{Hunk, Line, RichHunk, RichHunks};
+    use crate::render::DocumentDiffData;
+    use pretty_assertions::assert_eq;
+    use test_case::test_case;
+
+    #[test_case("a\nb", "a%0Ab" ; "newline")]
+    #[test_case("100%", "100%25" ; "percent")]
+    #[test_case("a: b, c", "a: b, c" ; "separators")]
+    fn test_escape_data(text: &str, expected: &str) {
+        assert_eq!(escape_data(text), expected);
+    }
+
+    #[test_case("src/a,b.rs", "src/a%2Cb.rs" ; "comma")]
+    #[test_case("C:\\a.rs", "C%3A\\a.rs" ; "colon")]
+    fn test_escape_property(text: &str, expected: &str) {
+        assert_eq!(escape_property(text), expected);
+    }
+
+    #[test]
+    fn render_commands() {
+        let data = DisplayData {
+            hunks: RichHunks(vec![
+                RichHunk::Old(Hunk(vec![Line::new(1)])),
+                RichHunk::New(Hunk(vec![Line::new(1), Line::new(2)])),
+            ]),
+            old: DocumentDiffData {
+                filename: "a.rs",
+                text: "fn main() {\n    a();\n}\n",
+                language: "rust",
+            },
+            new: DocumentDiffData {
+                filename: "b.rs",
+                text: "fn main() {\n    b();\n    c();\n}\n",
+                language: "rust",
+            },
+            moves: Vec::new(),
+            symbols: Vec::new(),
+            groups: vec![vec![0, 1]],
+        };
+        let renderer = GithubActions {
+            level: AnnotationLevel::Warning,
+            title: Some("Semantic change".into()),
+        };
+        let mut output = Vec::new();
+        renderer.render(&mut output, &data, None).unwrap();
+        let expected = "\
+::warning file=a.rs,line=2,endLine=2,col=1,endColumn=8,title=Semantic change::Deleted code
+::warning file=b.rs,line=2,endLine=3,col=1,endColumn=8,title=Semantic change::Added code
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
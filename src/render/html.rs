@@ -0,0 +1,340 @@
+use crate::diff::{Line, RichHunk};
+use crate::render::{default_option, line_segments, DisplayData, RenderState, Renderer};
+use anyhow::Result;
+use console::Term;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// The stylesheet that's inlined in every page
+const STYLESHEET: &str = "\
+body { font-family: sans-serif; margin: 2em; color: #24292f; }
+h1 { font-size: 1.25em; font-weight: normal; }
+h1 .old, h1 .new { font-family: monospace; }
+table.diff { border-collapse: collapse; width: 100%; table-layout: fixed; font-family: monospace; }
+table.diff col.line-number { width: 4em; }
+table.diff th { text-align: left; font-weight: normal; color: #57606a; padding: 1.5em 0.5em 0.25em; border-bottom: 1px solid #d0d7de; }
+table.diff td { padding: 0 0.5em; white-space: pre-wrap; overflow-wrap: anywhere; vertical-align: top; }
+td.line-number { text-align: right; color: #8c959f; user-select: none; }
+td.old { background: #ffebe9; }
+td.new { background: #e6ffec; }
+td.old .emphasis { background: #ffcecb; font-weight: bold; }
+td.new .emphasis { background: #abf2bc; font-weight: bold; }
+";
+
+/// A renderer that writes a standalone HTML page.
+///
+/// The page doesn't reference any external assets, so it can be attached to a ticket or archived
+/// and viewed anywhere that has a browser. Hunks that overlap in the edit script are displayed in
+/// a table with the old document on the left and the new document on the right, along with the
+/// line number of each line. Every diff is written into a single page, so the output of a
+/// directory diff is still one page.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct Html {
+    /// The title of the page, which defaults to `diffsitter`
+    #[serde(default = "default_option")]
+    pub title: Option<String>,
+    /// CSS rules that are added after the default stylesheet, which can be used to override it
+    pub extra_css: String,
+}
+
+/// The lines of a hunk group from one of the documents
+struct Pane<'a> {
+    /// The class of the pane's cells, either `old` or `new`
+    class: &'static str,
+    /// The full text of the document, split into lines
+    text_lines: Vec<&'a str>,
+}
+
+impl Renderer for Html {
+    fn render(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        term_info: Option<&Term>,
+    ) -> Result<()> {
+        // On its own, a diff is written as a complete page
+        let mut state = RenderState::default();
+        self.begin(writer, &mut state)?;
+        self.render_part(writer, data, term_info, &mut state)?;
+        self.end(writer, &mut state)
+    }
+
+    fn render_part(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        _term_info: Option<&Term>,
+        _state: &mut RenderState,
+    ) -> Result<()> {
+        let DisplayData {
+            hunks, old, new, ..
+        } = data;
+        writeln!(
+            writer,
+            "<h1><span class=\"old\">{}</span> &rarr; <span class=\"new\">{}</span></h1>",
+            escape_html(old.filename),
+            escape_html(new.filename)
+        )?;
+
+        if data.has_changes() {
+            let old_pane = Pane {
+                class: "old",
+                text_lines: old.text.lines().collect(),
+            };
+            let new_pane = Pane {
+                class: "new",
+                text_lines: new.text.lines().collect(),
+            };
+            writeln!(writer, "<table class=\"diff\">")?;
+            writeln!(
+                writer,
+                "<colgroup><col class=\"line-number\"><col><col class=\"line-number\"><col></colgroup>"
+            )?;
+            for group in data.hunk_groups() {
+                let (old_hunks, new_hunks): (Vec<_>, Vec<_>) = group
+                    .into_iter()
+                    .partition(|&hunk_idx| matches!(hunks.0[hunk_idx], RichHunk::Old(_)));
+                writeln!(writer, "<tbody>")?;
+                writeln!(
+                    writer,
+                    "<tr>{}{}</tr>",
+                    title_cell(data, &old_hunks),
+                    title_cell(data, &new_hunks)
+                )?;
+                let old_lines = hunk_lines(data, &old_hunks);
+                let new_lines = hunk_lines(data, &new_hunks);
+                for row in 0..old_lines.len().max(new_lines.len()) {
+                    writeln!(
+                        writer,
+                        "<tr>{}{}</tr>",
+                        old_pane.cells(old_lines.get(row).copied()),
+                        new_pane.cells(new_lines.get(row).copied())
+                    )?;
+                }
+                writeln!(writer, "</tbody>")?;
+            }
+            writeln!(writer, "</table>")?;
+        } else {
+            writeln!(writer, "<p>No changes</p>")?;
+        }
+        Ok(())
+    }
+
+    fn begin(&self, writer: &mut dyn Write, _state: &mut RenderState) -> Result<()> {
+        let title = self.title.as_deref().unwrap_or("diffsitter");
+        writeln!(writer, "<!DOCTYPE html>")?;
+        writeln!(writer, "<html lang=\"en\">")?;
+        writeln!(writer, "<head>")?;
+        writeln!(writer, "<meta charset=\"utf-8\">")?;
+        writeln!(writer, "<title>{}</title>", escape_html(title))?;
+        writeln!(writer, "<style>\n{STYLESHEET}{}\n</style>", self.extra_css)?;
+        writeln!(writer, "</head>")?;
+        writeln!(writer, "<body>")?;
+        Ok(())
+    }
+
+    fn end(&self, writer: &mut dyn Write, _state: &mut RenderState) -> Result<()> {
+        writeln!(writer, "</body>")?;
+        writeln!(writer, "</html>")?;
+        Ok(())
+    }
+}
+
+impl Pane<'_> {
+    /// Format the line number cell and the text cell for a line, which are empty if the line is
+    /// missing.
+    fn cells(&self, line: Option<&Line>) -> String {
+        let Some(line) = line else {
+            return "<td class=\"line-number\"></td><td></td>".into();
+        };
+        let text = self
+            .text_lines
+            .get(line.line_index)
+            .copied()
+            .unwrap_or_default();
+        // Entries are often split into graphemes, so we merge neighboring segments with the same
+        // emphasis rather than wrapping every grapheme in its own element
+        let mut runs: Vec<(String, bool)> = Vec::new();
+        for (segment, emphasized) in line_segments(text, line) {
+            match runs.last_mut() {
+                Some((run, run_emphasized)) if *run_emphasized == emphasized => {
+                    run.push_str(segment);
+                }
+                _ => runs.push((segment.to_string(), emphasized)),
+            }
+        }
+        let mut content = String::new();
+        for (run, emphasized) in runs {
+            if emphasized {
+                content.push_str(&format!(
+                    "<span class=\"emphasis\">{}</span>",
+                    escape_html(&run)
+                ));
+            } else {
+                content.push_str(&escape_html(&run));
+            }
+        }
+        format!(
+            "<td class=\"line-number\">{}</td><td class=\"{}\">{content}</td>",
+            line.line_index + 1,
+            self.class
+        )
+    }
+}
+
+/// Get every line of the given hunks, in order.
+fn hunk_lines<'a>(data: &'a DisplayData, hunk_indices: &[usize]) -> Vec<&'a Line<'a>> {
+    hunk_indices
+        .iter()
+        .flat_map(|&hunk_idx| data.hunks.0[hunk_idx].as_ref().0.iter())
+        .collect()
+}
+
+/// Format the header cell for the hunks from one of the documents in a group.
+///
+/// This has the range of lines the hunks span, numbered from 1, and the name of the symbol that
+/// encloses the first hunk, like `1 - 3 in main`.
+fn title_cell(data: &DisplayData, hunk_indices: &[usize]) -> String {
+    let (Some(&first_hunk), Some(&last_hunk)) = (hunk_indices.first(), hunk_indices.last()) else {
+        return "<th colspan=\"2\"></th>".into();
+    };
+    let first_line = data.hunks.0[first_hunk]
+        .as_ref()
+        .first_line()
+        .map(|line| line + 1);
+    let last_line = data.hunks.0[last_hunk]
+        .as_ref()
+        .last_line()
+        .map(|line| line + 1);
+    let mut title = match (first_line, last_line) {
+        (Some(first_line), Some(last_line)) if first_line != last_line => {
+            format!("{first_line} - {last_line}")
+        }
+        (Some(line), _) | (_, Some(line)) => line.to_string(),
+        (None, None) => String::new(),
+    };
+    if let Some(symbol) = data
+        .symbols
+        .get(first_hunk)
+        .and_then(|symbols| symbols.last())
+    {
+        title.push_str(&format!(" in {}", symbol.name));
+    }
+    format!("<th colspan=\"2\">{}</th>", escape_html(&title))
+}
+
+/// Escape the characters that have a special meaning in HTML.
+pub fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{Hunk, RichHunks};
+    use crate::render::DocumentDiffData;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_escape_html() {
+        assert_eq!(
+            escape_html("if a < b && c > \"d\" {}"),
+            "if a &lt; b &amp;&amp; c &gt; &quot;d&quot; {}"
+        );
+        assert_eq!(escape_html("it's"), "it&#39;s");
+    }
+
+    #[test]
+    fn render_page() {
+        let data = DisplayData {
+            hunks: RichHunks(vec![
+                RichHunk::New(Hunk(vec![Line::new(1)])),
+                RichHunk::Old(Hunk(vec![Line::new(1), Line::new(2)])),
+            ]),
+            old: DocumentDiffData {
+                filename: "<old>.rs",
+                text: "fn main() {\n    a();\n    b();\n}\n",
+                language: "rust",
+            },
+            new: DocumentDiffData {
+                filename: "new.rs",
+                text: "fn main() {\n    c();\n}\n",
+                language: "rust",
+            },
+            moves: Vec::new(),
+            symbols: Vec::new(),
+            groups: vec![vec![1, 0]],
+        };
+        let mut output = Vec::new();
+        Html::default().render(&mut output, &data, None).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert!(output.starts_with("<!DOCTYPE html>"));
+        assert!(output.contains("<title>diffsitter</title>"));
+        assert!(output.contains("<span class=\"old\">&lt;old&gt;.rs</span>"));
+        assert!(output.contains("<th colspan=\"2\">2 - 3</th><th colspan=\"2\">2</th>"));
+        // The old and new hunks from the group are displayed side by side
+        assert!(output.contains(
+            "<tr><td class=\"line-number\">2</td><td class=\"old\">    a();</td>\
+             <td class=\"line-number\">2</td><td class=\"new\">    c();</td></tr>"
+        ));
+        assert!(output.contains(
+            "<tr><td class=\"line-number\">3</td><td class=\"old\">    b();</td>\
+             <td class=\"line-number\"></td><td></td></tr>"
+        ));
+        assert!(output.trim_end().ends_with("</html>"));
+    }
+
+    #[test]
+    fn render_single_page() {
+        let data = DisplayData {
+            hunks: RichHunks(vec![RichHunk::New(Hunk(vec![Line::new(0)]))]),
+            old: DocumentDiffData {
+                filename: "a.rs",
+                text: "",
+                language: "rust",
+            },
+            new: DocumentDiffData {
+                filename: "b.rs",
+                text: "fn main() {}\n",
+                language: "rust",
+            },
+            moves: Vec::new(),
+            symbols: Vec::new(),
+            groups: vec![vec![0]],
+        };
+        let renderer = Html {
+            title: Some("Review".into()),
+            ..Html::default()
+        };
+        let mut output = Vec::new();
+        let mut state = RenderState::default();
+        renderer.begin(&mut output, &mut state).unwrap();
+        // Rendering two diffs still writes a single page
+        renderer
+            .render_part(&mut output, &data, None, &mut state)
+            .unwrap();
+        renderer
+            .render_part(&mut output, &data, None, &mut state)
+            .unwrap();
+        renderer.end(&mut output, &mut state).unwrap();
+        let output = String::from_utf8(output).unwrap();
+
+        assert_eq!(output.matches("<!DOCTYPE html>").count(), 1);
+        assert_eq!(output.matches("</html>").count(), 1);
+        assert_eq!(output.matches("<table class=\"diff\">").count(), 2);
+        assert!(output.contains("<title>Review</title>"));
+    }
+}
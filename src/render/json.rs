@@ -22,7 +22,9 @@ impl Renderer for Json {
         _term_info: Option<&Term>,
     ) -> anyhow::Result<()> {
         let json_str = self.generate_json_str(data)?;
-        write!(writer, "{}", &json_str)?;
+        // End with a newline so the output of multiple diffs can be read as JSON lines when pretty
+        // printing is disabled
+        writeln!(writer, "{}", &json_str)?;
         Ok(())
     }
 }
@@ -39,3 +41,38 @@ impl Json {
         serde_json::to_string(data)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{Hunk, Line, RichHunk, RichHunks};
+    use crate::render::DocumentDiffData;
+    use pretty_assertions::assert_eq;
+    use serde_json::Value;
+
+    #[test]
+    fn render_fields() {
+        let data = DisplayData {
+            hunks: RichHunks(vec![RichHunk::New(Hunk(vec![Line::new(0)]))]),
+            old: DocumentDiffData {
+                filename: "a.rs",
+                text: "",
+                language: "rust",
+            },
+            new: DocumentDiffData {
+                filename: "b.rs",
+                text: "fn main() {}\n",
+                language: "rust",
+            },
+            moves: Vec::new(),
+            symbols: Vec::new(),
+            groups: vec![vec![0]],
+        };
+        let mut output = Vec::new();
+        Json::default().render(&mut output, &data, None).unwrap();
+        let json: Value = serde_json::from_slice(&output).unwrap();
+        let mut fields: Vec<_> = json.as_object().unwrap().keys().collect();
+        fields.sort();
+        assert_eq!(fields, ["hunks", "moves", "new", "old", "symbols"]);
+    }
+}
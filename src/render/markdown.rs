@@ -0,0 +1,266 @@
+use crate::diff::RichHunk;
+use crate::render::{
+    html::escape_html,
+    line_segments,
+    unified::{line_range, move_notes},
+    DisplayData, Renderer,
+};
+use anyhow::Result;
+use console::{measure_text_width, Term};
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// A renderer that writes GitHub and GitLab flavored Markdown, which is useful for posting diffs
+/// in pull request comments.
+///
+/// Each pair of files gets a heading, and each hunk is displayed in a collapsible `<details>`
+/// element with a fenced code block that's tagged with the language of the file, so it's syntax
+/// highlighted. Code blocks can't contain formatting, so the emphasized text on each line is
+/// marked with carets on the line below it.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Markdown {
+    /// The level of the heading for each pair of files, from 1 to 6
+    pub heading_level: usize,
+    /// Whether the hunks are expanded by default
+    pub expand: bool,
+    /// Whether to mark the emphasized text on each line with carets
+    pub mark_emphasis: bool,
+}
+
+impl Default for Markdown {
+    fn default() -> Self {
+        Markdown {
+            heading_level: 3,
+            expand: false,
+            mark_emphasis: true,
+        }
+    }
+}
+
+impl Renderer for Markdown {
+    fn render(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        _term_info: Option<&Term>,
+    ) -> Result<()> {
+        let DisplayData {
+            hunks, old, new, ..
+        } = data;
+        let heading = "#".repeat(self.heading_level.clamp(1, 6));
+        writeln!(
+            writer,
+            "{heading} {} → {}\n",
+            code_span(old.filename),
+            code_span(new.filename)
+        )?;
+        if !data.has_changes() {
+            writeln!(writer, "No changes\n")?;
+            return Ok(());
+        }
+
+        let old_lines: Vec<_> = old.text.lines().collect();
+        let new_lines: Vec<_> = new.text.lines().collect();
+        let details = if self.expand {
+            "<details open>"
+        } else {
+            "<details>"
+        };
+
+        // Each deletion is displayed right before the addition that replaced it
+        for hunk_idx in data.hunk_groups().concat() {
+            let (lines, document, action) = match &hunks.0[hunk_idx] {
+                RichHunk::Old(_) => (&old_lines, old, "Deleted"),
+                RichHunk::New(_) => (&new_lines, new, "Added"),
+            };
+            let hunk = hunks.0[hunk_idx].as_ref();
+            let (Some(first_line), Some(last_line)) = (hunk.first_line(), hunk.last_line()) else {
+                continue;
+            };
+
+            let mut summary = format!(
+                "{action} {} {}",
+                if first_line == last_line {
+                    "line"
+                } else {
+                    "lines"
+                },
+                // Lines are numbered from 1 like in editors
+                line_range(first_line + 1, last_line + 1)
+            );
+            if let Some(symbol) = data
+                .symbols
+                .get(hunk_idx)
+                .and_then(|symbols| symbols.last())
+            {
+                summary.push_str(&format!(" in <code>{}</code>", escape_html(&symbol.name)));
+            }
+            let notes: Vec<_> = move_notes(&data.moves, hunk_idx, 1).collect();
+            if !notes.is_empty() {
+                summary.push_str(&format!(" ({})", notes.join(", ")));
+            }
+
+            let mut code = String::new();
+            for line in &hunk.0 {
+                let text = lines.get(line.line_index).copied().unwrap_or_default();
+                code.push_str(text);
+                code.push('\n');
+                if self.mark_emphasis {
+                    let carets = caret_line(&line_segments(text, line));
+                    if !carets.is_empty() {
+                        code.push_str(&carets);
+                        code.push('\n');
+                    }
+                }
+            }
+            let fence = code_fence(&code);
+
+            writeln!(writer, "{details}")?;
+            writeln!(writer, "<summary>{summary}</summary>\n")?;
+            writeln!(
+                writer,
+                "{fence}{}\n{code}{fence}\n",
+                fence_language(document.language)
+            )?;
+            writeln!(writer, "</details>\n")?;
+        }
+        Ok(())
+    }
+}
+
+/// Create a line that marks the emphasized segments of a line with carets.
+///
+/// Other characters are replaced with spaces of the same width, except for tabs, which are kept
+/// so the carets line up with the text. This returns an empty string if nothing is emphasized.
+fn caret_line(segments: &[(&str, bool)]) -> String {
+    let mut carets = String::new();
+    for &(text, emphasized) in segments {
+        for c in text.chars() {
+            if c == '\t' {
+                carets.push('\t');
+            } else {
+                let marker = if emphasized { "^" } else { " " };
+                carets.push_str(&marker.repeat(measure_text_width(c.encode_utf8(&mut [0; 4]))));
+            }
+        }
+    }
+    if carets.contains('^') {
+        carets.trim_end().to_string()
+    } else {
+        String::new()
+    }
+}
+
+/// Get a fence for a code block that's longer than any run of backticks in the code.
+fn code_fence(code: &str) -> String {
+    "`".repeat((longest_backtick_run(code) + 1).max(3))
+}
+
+/// Format text as inline code, using enough backticks that the text can contain backticks.
+fn code_span(text: &str) -> String {
+    let fence = "`".repeat(longest_backtick_run(text) + 1);
+    // Padding keeps a backtick at the start or end of the text from merging with the fence
+    if text.starts_with('`') || text.ends_with('`') {
+        format!("{fence} {text} {fence}")
+    } else {
+        format!("{fence}{text}{fence}")
+    }
+}
+
+/// Get the length of the longest run of backticks in some text.
+fn longest_backtick_run(text: &str) -> usize {
+    text.split(|c| c != '`')
+        .map(str::len)
+        .max()
+        .unwrap_or_default()
+}
+
+/// Get the name that Markdown renderers use to highlight a language, which is usually the same as
+/// the name of the grammar.
+fn fence_language(language: &str) -> &str {
+    match language {
+        "c_sharp" => "csharp",
+        language => language,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{Hunk, Line, RichHunks};
+    use crate::render::DocumentDiffData;
+    use pretty_assertions::assert_eq;
+    use test_case::test_case;
+
+    #[test_case(&[("let x = 1;", false)], "" ; "no emphasis")]
+    #[test_case(&[("let ", false), ("x", true), (" = ", false), ("10", true), (";", false)], "    ^   ^^" ; "emphasis")]
+    #[test_case(&[("\t", false), ("ab", true)], "\t^^" ; "tabs")]
+    #[test_case(&[("表", false), ("x", true)], "  ^" ; "wide characters")]
+    fn test_caret_line(segments: &[(&str, bool)], expected: &str) {
+        assert_eq!(caret_line(segments), expected);
+    }
+
+    #[test_case("fn main() {}", "```" ; "no backticks")]
+    #[test_case("let s = \"```\";", "````" ; "fence in code")]
+    fn test_code_fence(code: &str, expected: &str) {
+        assert_eq!(code_fence(code), expected);
+    }
+
+    #[test_case("a.rs", "`a.rs`")]
+    #[test_case("a`b.rs", "``a`b.rs``")]
+    #[test_case("`a", "`` `a ``")]
+    fn test_code_span(text: &str, expected: &str) {
+        assert_eq!(code_span(text), expected);
+    }
+
+    #[test]
+    fn render_hunks() {
+        let data = DisplayData {
+            hunks: RichHunks(vec![
+                RichHunk::New(Hunk(vec![Line::new(1)])),
+                RichHunk::Old(Hunk(vec![Line::new(1), Line::new(2)])),
+            ]),
+            old: DocumentDiffData {
+                filename: "a.rs",
+                text: "fn main() {\n    a();\n    b();\n}\n",
+                language: "rust",
+            },
+            new: DocumentDiffData {
+                filename: "b.rs",
+                text: "fn main() {\n    c();\n}\n",
+                language: "rust",
+            },
+            moves: Vec::new(),
+            symbols: Vec::new(),
+            groups: vec![vec![1, 0]],
+        };
+        let mut output = Vec::new();
+        Markdown::default()
+            .render(&mut output, &data, None)
+            .unwrap();
+        let expected = "### `a.rs` → `b.rs`
+
+<details>
+<summary>Deleted lines 2 - 3</summary>
+
+```rust
+    a();
+    b();
+```
+
+</details>
+
+<details>
+<summary>Added line 2</summary>
+
+```rust
+    c();
+```
+
+</details>
+
+";
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
@@ -8,17 +8,38 @@
 //!
 //! This module also defines utilities that may be useful for `Renderer` implementations.
 
+mod annotations;
+mod checkstyle;
+mod github_actions;
+mod html;
 mod json;
+mod markdown;
+mod patch;
+mod quickfix;
+mod sarif;
+mod side_by_side;
+mod stat;
 mod unified;
 
+use self::checkstyle::Checkstyle;
+use self::github_actions::GithubActions;
+use self::html::Html;
 use self::json::Json;
-use crate::diff::RichHunks;
-use anyhow::anyhow;
+use self::markdown::Markdown;
+use self::patch::Patch;
+use self::quickfix::Quickfix;
+use self::sarif::Sarif;
+use crate::diff::{Line, Move, RichHunks};
+use crate::symbols::Symbol;
+use anyhow::{anyhow, bail, Context};
 use console::{Color, Style, Term};
 use enum_dispatch::enum_dispatch;
 use serde::{Deserialize, Serialize};
-use std::io::Write;
-use strum::{self, Display, EnumIter, EnumString, IntoEnumIterator};
+use serde_json::Value;
+use side_by_side::SideBySide;
+use stat::{Stat, StatFormat};
+use std::{collections::HashMap, io::Write, str::FromStr};
+use strum::{self, Display, EnumIter, EnumString};
 use unified::Unified;
 
 /// The parameters required to display a diff for a particular document
@@ -28,6 +49,8 @@ pub struct DocumentDiffData<'a> {
     pub filename: &'a str,
     /// The full text of the document
     pub text: &'a str,
+    /// The language the document was parsed with, like `rust`
+    pub language: &'a str,
 }
 
 /// The parameters a [Renderer] instance receives to render a diff.
@@ -39,6 +62,44 @@ pub struct DisplayData<'a> {
     pub old: DocumentDiffData<'a>,
     /// The parameters that correspond to the new document
     pub new: DocumentDiffData<'a>,
+    /// The blocks of code that were moved, which refer to hunks in `hunks`
+    pub moves: Vec<Move>,
+    /// The symbols that enclose each hunk, from the outermost to the innermost, in the same order
+    /// as `hunks`
+    pub symbols: Vec<Vec<Symbol>>,
+    /// The hunks that overlap in the edit script, as groups of indices into `hunks`
+    ///
+    /// Each group has its old hunks followed by its new hunks, and the groups are in the order
+    /// they appear in the edit script. This is only used to lay out the diff, so it isn't part of
+    /// the JSON output.
+    #[serde(skip)]
+    pub groups: Vec<Vec<usize>>,
+}
+
+impl DisplayData<'_> {
+    /// Whether the documents have any differences
+    #[must_use]
+    pub fn has_changes(&self) -> bool {
+        !self.hunks.0.is_empty()
+    }
+
+    /// The groups of hunks that overlap in the edit script.
+    ///
+    /// This is the same as `groups`, unless `groups` doesn't have every hunk exactly once (like
+    /// when the display data was constructed by hand), in which case every hunk is put in its own
+    /// group.
+    #[must_use]
+    pub fn hunk_groups(&self) -> Vec<Vec<usize>> {
+        let mut hunk_indices = self.groups.concat();
+        hunk_indices.sort_unstable();
+        if hunk_indices.into_iter().eq(0..self.hunks.0.len()) {
+            self.groups.clone()
+        } else {
+            (0..self.hunks.0.len())
+                .map(|hunk_idx| vec![hunk_idx])
+                .collect()
+        }
+    }
 }
 
 #[enum_dispatch]
@@ -48,6 +109,15 @@ pub struct DisplayData<'a> {
 pub enum Renderers {
     Unified,
     Json,
+    SideBySide,
+    Stat,
+    Html,
+    Patch,
+    Markdown,
+    GithubActions,
+    Checkstyle,
+    Sarif,
+    Quickfix,
 }
 
 impl Default for Renderers {
@@ -56,11 +126,34 @@ impl Default for Renderers {
     }
 }
 
+impl Renderers {
+    /// Whether the renderer writes a structured format, like JSON, HTML or a patch, which means
+    /// nothing else should be written to its output
+    #[must_use]
+    pub fn is_structured(&self) -> bool {
+        match self {
+            Renderers::Json(_)
+            | Renderers::Html(_)
+            | Renderers::Patch(_)
+            | Renderers::Markdown(_)
+            | Renderers::GithubActions(_)
+            | Renderers::Checkstyle(_)
+            | Renderers::Sarif(_)
+            | Renderers::Quickfix(_) => true,
+            Renderers::Stat(stat) => stat.format == StatFormat::Json,
+            Renderers::Unified(_) | Renderers::SideBySide(_) => false,
+        }
+    }
+}
+
 /// An interface that renders given diff data.
 #[enum_dispatch(Renderers)]
 pub trait Renderer {
     /// Render a diff.
     ///
+    /// The output has to be complete on its own, so renderers that write a header or a footer in
+    /// [`begin`](Renderer::begin) and [`end`](Renderer::end) write them here too.
+    ///
     /// We use anyhow for errors so errors are free form for implementors, as they are not
     /// recoverable.
     ///
@@ -74,6 +167,83 @@ pub trait Renderer {
         data: &DisplayData,
         term_info: Option<&Term>,
     ) -> anyhow::Result<()>;
+
+    /// Render a diff as part of an output that contains multiple diffs.
+    ///
+    /// The output is started with [`begin`](Renderer::begin) and finished with
+    /// [`end`](Renderer::end), and `state` is shared by every call for the same output. This is the
+    /// same as [`render`](Renderer::render) by default.
+    fn render_part(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        term_info: Option<&Term>,
+        _state: &mut RenderState,
+    ) -> anyhow::Result<()> {
+        self.render(writer, data, term_info)
+    }
+
+    /// Write anything that comes before the diffs.
+    ///
+    /// This is called once before rendering any diffs, even when multiple pairs of documents are
+    /// diffed, so renderers that write a single document for every diff (like Checkstyle XML) can
+    /// write its header here. This does nothing by default.
+    fn begin(&self, _writer: &mut dyn Write, _state: &mut RenderState) -> anyhow::Result<()> {
+        Ok(())
+    }
+
+    /// Write anything that comes after the diffs.
+    ///
+    /// This is called once after every diff has been rendered. This does nothing by default.
+    fn end(&self, _writer: &mut dyn Write, _state: &mut RenderState) -> anyhow::Result<()> {
+        Ok(())
+    }
+}
+
+/// What a renderer has written so far to an output that contains multiple diffs
+///
+/// This is kept separately from the renderer, so rendering doesn't change the renderer's
+/// configuration.
+#[derive(Debug, Clone, PartialEq, Eq, Default)]
+pub struct RenderState {
+    /// The number of items (like SARIF results) that have been written to the output
+    pub items_written: usize,
+}
+
+/// Split the text of a line into segments of regular and emphasized text.
+///
+/// `text` is the full text of the line that corresponds to `line`. Each segment is returned with a
+/// flag that indicates whether it should be emphasized. Entries that span multiple lines are
+/// emphasized until the end of the line.
+fn line_segments<'a>(text: &'a str, line: &Line) -> Vec<(&'a str, bool)> {
+    let mut segments = Vec::new();
+    // All indices are raw byte offsets, splitting on graphemes was taken care of when processing
+    // the AST nodes.
+    let mut printed_chars = 0;
+
+    for entry in &line.entries {
+        let start = entry
+            .start_position()
+            .column
+            .clamp(printed_chars, text.len());
+        let end = if entry.end_position().row == line.line_index {
+            entry.end_position().column.clamp(start, text.len())
+        } else {
+            text.len()
+        };
+
+        if start > printed_chars {
+            segments.push((&text[printed_chars..start], false));
+        }
+        if end > start {
+            segments.push((&text[start..end], true));
+        }
+        printed_chars = end;
+    }
+    if printed_chars < text.len() {
+        segments.push((&text[printed_chars..], false));
+    }
+    segments
 }
 
 /// A copy of the [Color](console::Color) enum so we can serialize using serde, and get around the
@@ -204,11 +374,27 @@ impl Default for HighlightColors {
 pub struct RenderConfig {
     /// The default diff renderer to use.
     ///
-    /// This is used if no renderer is specified at the command line.
+    /// This is used if no renderer is specified at the command line. This can be the name of a
+    /// renderer or the tag of a custom renderer.
     default: String,
 
     unified: unified::Unified,
     json: json::Json,
+    side_by_side: side_by_side::SideBySide,
+    stat: stat::Stat,
+    html: html::Html,
+    patch: patch::Patch,
+    markdown: markdown::Markdown,
+    github_actions: github_actions::GithubActions,
+    checkstyle: checkstyle::Checkstyle,
+    sarif: sarif::Sarif,
+    quickfix: quickfix::Quickfix,
+
+    /// Custom renderer configurations, keyed by their tag.
+    ///
+    /// Each entry must have a `type` field with the name of the renderer it configures. Any
+    /// options that aren't set are inherited from the config section for that renderer.
+    custom: HashMap<String, Value>,
 }
 
 impl Default for RenderConfig {
@@ -218,6 +404,16 @@ impl Default for RenderConfig {
             default: default_renderer.to_string(),
             unified: Unified::default(),
             json: Json::default(),
+            side_by_side: SideBySide::default(),
+            stat: Stat::default(),
+            html: Html::default(),
+            patch: Patch::default(),
+            markdown: Markdown::default(),
+            github_actions: GithubActions::default(),
+            checkstyle: Checkstyle::default(),
+            sarif: Sarif::default(),
+            quickfix: Quickfix::default(),
+            custom: HashMap::new(),
         }
     }
 }
@@ -228,25 +424,118 @@ impl RenderConfig {
     /// If the tag is not specified this will fall back to the default renderer. This is a
     /// relatively expensive operation so it should be used once and the result should be saved.
     pub fn get_renderer(self, tag: Option<String>) -> anyhow::Result<Renderers> {
-        if let Some(t) = tag {
-            let cand_enum = Renderers::iter().find(|e| e.to_string() == t);
-            match cand_enum {
-                None => Err(anyhow!("'{}' is not a valid renderer", &t)),
-                Some(renderer) => Ok(renderer),
+        let tag = tag.as_ref().unwrap_or(&self.default);
+        self.renderer_for_tag(tag)
+    }
+
+    /// Check that the default renderer and every custom renderer in the config are valid.
+    ///
+    /// This lets us report config errors when the config is loaded rather than when a particular
+    /// renderer is requested.
+    pub fn validate(&self) -> anyhow::Result<()> {
+        let mut tags: Vec<_> = self.custom.keys().collect();
+        tags.sort();
+
+        for tag in tags {
+            if Renderers::from_str(tag).is_ok() {
+                bail!("The custom renderer tag '{tag}' conflicts with a builtin renderer");
+            }
+            self.renderer_for_tag(tag)?;
+        }
+        self.renderer_for_tag(&self.default)
+            .context("The default renderer is invalid")?;
+        Ok(())
+    }
+
+    /// Get the renderer for a tag, which can be either a custom tag or the name of a renderer.
+    fn renderer_for_tag(&self, tag: &str) -> anyhow::Result<Renderers> {
+        if let Some(custom_config) = self.custom.get(tag) {
+            return self.custom_renderer(tag, custom_config);
+        }
+        self.configured_renderer(tag)
+            .ok_or_else(|| anyhow!("'{}' is not a valid renderer", tag))
+    }
+
+    /// Get a renderer by name with the options from its section of the config.
+    fn configured_renderer(&self, name: &str) -> Option<Renderers> {
+        let renderer = match Renderers::from_str(name).ok()? {
+            Renderers::Unified(_) => self.unified.clone().into(),
+            Renderers::Json(_) => self.json.clone().into(),
+            Renderers::SideBySide(_) => self.side_by_side.clone().into(),
+            Renderers::Stat(_) => self.stat.clone().into(),
+            Renderers::Html(_) => self.html.clone().into(),
+            Renderers::Patch(_) => self.patch.clone().into(),
+            Renderers::Markdown(_) => self.markdown.clone().into(),
+            Renderers::GithubActions(_) => self.github_actions.clone().into(),
+            Renderers::Checkstyle(_) => self.checkstyle.clone().into(),
+            Renderers::Sarif(_) => self.sarif.clone().into(),
+            Renderers::Quickfix(_) => self.quickfix.clone().into(),
+        };
+        Some(renderer)
+    }
+
+    /// Create a renderer from a custom renderer config.
+    ///
+    /// The custom config is layered on top of the config section for the renderer it names.
+    fn custom_renderer(&self, tag: &str, custom_config: &Value) -> anyhow::Result<Renderers> {
+        let renderer_type = custom_config
+            .get("type")
+            .and_then(Value::as_str)
+            .ok_or_else(|| anyhow!("The custom renderer '{tag}' must specify a `type`"))?;
+        let base = self.configured_renderer(renderer_type).ok_or_else(|| {
+            anyhow!("The custom renderer '{tag}' has an invalid type '{renderer_type}'")
+        })?;
+        let mut value = serde_json::to_value(base)?;
+        merge_json(&mut value, custom_config);
+        serde_json::from_value(value)
+            .with_context(|| format!("Failed to parse the custom renderer '{tag}'"))
+    }
+}
+
+/// Recursively merge the values from `overrides` into `base`.
+///
+/// Objects are merged key by key, any other value from `overrides` replaces the value in `base`.
+fn merge_json(base: &mut Value, overrides: &Value) {
+    match (base, overrides) {
+        (Value::Object(base), Value::Object(overrides)) => {
+            for (key, value) in overrides {
+                match base.get_mut(key) {
+                    Some(base_value) => merge_json(base_value, value),
+                    None => {
+                        base.insert(key.clone(), value.clone());
+                    }
+                }
             }
-        } else {
-            Ok(Renderers::default())
         }
+        (base, overrides) => *base = overrides.clone(),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use serde_json::json;
     use test_case::test_case;
 
+    #[test]
+    fn renderers_are_sync() {
+        // Renderers are part of the config, which library users share between threads
+        fn assert_sync<T: Sync>() {}
+        assert_sync::<Renderers>();
+        assert_sync::<RenderConfig>();
+    }
+
     #[test_case("unified")]
     #[test_case("json")]
+    #[test_case("side_by_side")]
+    #[test_case("stat")]
+    #[test_case("html")]
+    #[test_case("patch")]
+    #[test_case("markdown")]
+    #[test_case("github_actions")]
+    #[test_case("checkstyle")]
+    #[test_case("sarif")]
+    #[test_case("quickfix")]
     fn test_get_renderer_custom_tag(tag: &str) {
         let cfg = RenderConfig::default();
         let res = cfg.get_renderer(Some(tag.into()));
@@ -259,4 +548,67 @@ mod tests {
         let res = cfg.get_renderer(None);
         assert_eq!(res.unwrap(), Renderers::default());
     }
+
+    /// Create a render config from a JSON value, as if it were read from a config file
+    fn config_from_json(value: Value) -> RenderConfig {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn test_get_renderer_uses_config() {
+        let mut cfg = RenderConfig::default();
+        cfg.unified.context_lines = 3;
+        let expected = Renderers::Unified(cfg.unified.clone());
+        assert_eq!(cfg.get_renderer(Some("unified".into())).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_get_renderer_custom_profile() {
+        let mut cfg = config_from_json(json!({
+            "custom": {
+                "short": {
+                    "type": "unified",
+                    "deletion": { "prefix": "<" },
+                },
+            },
+        }));
+        cfg.unified.context_lines = 2;
+        let mut expected = Unified {
+            context_lines: 2,
+            ..Unified::default()
+        };
+        expected.deletion.prefix = "<".into();
+        assert_eq!(
+            cfg.get_renderer(Some("short".into())).unwrap(),
+            Renderers::Unified(expected)
+        );
+    }
+
+    #[test]
+    fn test_default_custom_profile() {
+        let cfg = config_from_json(json!({
+            "default": "pretty",
+            "custom": {
+                "pretty": {
+                    "type": "json",
+                    "pretty_print": true,
+                },
+            },
+        }));
+        cfg.validate().unwrap();
+        assert_eq!(
+            cfg.get_renderer(None).unwrap(),
+            Renderers::Json(Json { pretty_print: true })
+        );
+    }
+
+    #[test_case(json!({"default": "missing"}) ; "unknown default")]
+    #[test_case(json!({"custom": {"foo": {"deletion": {}}}}) ; "missing type")]
+    #[test_case(json!({"custom": {"foo": {"type": "missing"}}}) ; "unknown type")]
+    #[test_case(json!({"custom": {"foo": {"type": "json", "pretty_print": "yes"}}}) ; "invalid option")]
+    #[test_case(json!({"custom": {"json": {"type": "json"}}}) ; "conflicting tag")]
+    fn test_validate_invalid_config(value: Value) {
+        let cfg = config_from_json(value);
+        assert!(cfg.validate().is_err());
+    }
 }
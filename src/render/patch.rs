@@ -0,0 +1,366 @@
+use crate::diff::{Engine, Myers, RichHunk};
+use crate::input_processing::EditType;
+use crate::render::{DisplayData, Renderer};
+use anyhow::Result;
+use console::Term;
+use serde::{Deserialize, Serialize};
+use std::{collections::HashMap, io::Write};
+
+/// The path that stands in for a document that doesn't exist, like the old version of a new file
+const NULL_PATH: &str = "/dev/null";
+
+/// A renderer that writes a standard unified patch, which can be applied with `patch` or
+/// `git apply`.
+///
+/// Every line with a semantic edit is part of the patch, and unchanged lines are used as context.
+/// Applying a patch has to reproduce the new document exactly, so lines that differ in ways the
+/// semantic diff ignores (like whitespace) are also part of the patch.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Patch {
+    /// The number of unchanged lines to include before and after each change, like `diff -U N`
+    pub context_lines: usize,
+    /// The prefix to add to the path of the old document, like git's `--src-prefix`
+    pub old_prefix: String,
+    /// The prefix to add to the path of the new document, like git's `--dst-prefix`
+    pub new_prefix: String,
+}
+
+impl Default for Patch {
+    fn default() -> Self {
+        Patch {
+            context_lines: 3,
+            old_prefix: "a/".into(),
+            new_prefix: "b/".into(),
+        }
+    }
+}
+
+/// How a line is compared when aligning the lines of the two documents
+///
+/// Lines with semantic edits can never be matched with a line from the other document, and other
+/// lines are matched if their text is identical.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineKey<'a> {
+    /// A line in the old document with deleted entries
+    Deleted(usize),
+    /// A line in the new document with added entries
+    Added(usize),
+    /// A line without any edits
+    Text(&'a str),
+}
+
+/// An operation that turns the old document into the new document, one line at a time
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum LineOp {
+    /// Keep a line from the old document, which is the same as a line in the new document
+    Keep { old: usize, new: usize },
+    /// Delete a line from the old document
+    Delete(usize),
+    /// Insert a line from the new document
+    Insert(usize),
+}
+
+impl Renderer for Patch {
+    fn render(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        _term_info: Option<&Term>,
+    ) -> Result<()> {
+        // We keep the line endings so the patch reproduces them exactly
+        let old_lines: Vec<_> = data.old.text.split_inclusive('\n').collect();
+        let new_lines: Vec<_> = data.new.text.split_inclusive('\n').collect();
+        let ops = line_ops(data, &old_lines, &new_lines);
+        if ops.iter().all(|op| matches!(op, LineOp::Keep { .. })) {
+            return Ok(());
+        }
+
+        writeln!(
+            writer,
+            "--- {}",
+            patch_path(&self.old_prefix, data.old.filename)
+        )?;
+        writeln!(
+            writer,
+            "+++ {}",
+            patch_path(&self.new_prefix, data.new.filename)
+        )?;
+
+        // The semantic hunks that each edited line is part of, so we can name the symbol that
+        // encloses a change
+        let mut old_hunks = HashMap::new();
+        let mut new_hunks = HashMap::new();
+        for (hunk_idx, hunk) in data.hunks.0.iter().enumerate() {
+            let line_hunks = match hunk {
+                RichHunk::Old(_) => &mut old_hunks,
+                RichHunk::New(_) => &mut new_hunks,
+            };
+            for line in &hunk.as_ref().0 {
+                line_hunks.entry(line.line_index).or_insert(hunk_idx);
+            }
+        }
+
+        for window in change_windows(&ops, self.context_lines) {
+            let window_ops = &ops[window.clone()];
+            let (old_start, old_count) = line_range(&ops[..window.start], window_ops, true);
+            let (new_start, new_count) = line_range(&ops[..window.start], window_ops, false);
+            write!(
+                writer,
+                "@@ -{old_start},{old_count} +{new_start},{new_count} @@"
+            )?;
+            let symbol = window_ops
+                .iter()
+                .find_map(|op| match op {
+                    LineOp::Keep { .. } => None,
+                    LineOp::Delete(old) => Some(old_hunks.get(old)),
+                    LineOp::Insert(new) => Some(new_hunks.get(new)),
+                })
+                .flatten()
+                .and_then(|&hunk_idx| data.symbols.get(hunk_idx))
+                .and_then(|symbols| symbols.last());
+            if let Some(symbol) = symbol {
+                write!(writer, " {}", symbol.name)?;
+            }
+            writeln!(writer)?;
+
+            for op in window_ops {
+                let (marker, line) = match *op {
+                    LineOp::Keep { old, .. } => (' ', old_lines[old]),
+                    LineOp::Delete(old) => ('-', old_lines[old]),
+                    LineOp::Insert(new) => ('+', new_lines[new]),
+                };
+                write!(writer, "{marker}{line}")?;
+                if !line.ends_with('\n') {
+                    writeln!(writer)?;
+                    writeln!(writer, "\\ No newline at end of file")?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Align the lines of the two documents, using the semantic hunks to decide which lines were
+/// edited.
+///
+/// Lines with semantic edits are always deleted or inserted, and the remaining lines are kept if
+/// they're identical to a line in the other document.
+fn line_ops(data: &DisplayData, old_lines: &[&str], new_lines: &[&str]) -> Vec<LineOp> {
+    let mut old_keys: Vec<_> = old_lines.iter().map(|line| LineKey::Text(line)).collect();
+    let mut new_keys: Vec<_> = new_lines.iter().map(|line| LineKey::Text(line)).collect();
+    for hunk in &data.hunks.0 {
+        for line in &hunk.as_ref().0 {
+            let (keys, key) = match hunk {
+                RichHunk::Old(_) => (&mut old_keys, LineKey::Deleted(line.line_index)),
+                RichHunk::New(_) => (&mut new_keys, LineKey::Added(line.line_index)),
+            };
+            if let Some(slot) = keys.get_mut(line.line_index) {
+                *slot = key;
+            }
+        }
+    }
+
+    // The edit script lists the edits to each document in order, so we can tell which lines were
+    // edited by walking through the lines and the edits together
+    let edits = Myers::default().diff(&old_keys, &new_keys);
+    let mut deleted = vec![false; old_keys.len()];
+    let mut inserted = vec![false; new_keys.len()];
+    for (is_deletion, keys, edited) in [
+        (true, &old_keys, &mut deleted),
+        (false, &new_keys, &mut inserted),
+    ] {
+        let mut edits = edits
+            .iter()
+            .filter_map(|edit| match edit {
+                EditType::Deletion(key) if is_deletion => Some(*key),
+                EditType::Addition(key) if !is_deletion => Some(*key),
+                _ => None,
+            })
+            .peekable();
+        for (key, is_edited) in keys.iter().zip(edited.iter_mut()) {
+            if edits.next_if(|edit| std::ptr::eq(*edit, key)).is_some() {
+                *is_edited = true;
+            }
+        }
+    }
+
+    let mut ops = Vec::with_capacity(old_keys.len() + new_keys.len());
+    let (mut old, mut new) = (0, 0);
+    while old < old_keys.len() || new < new_keys.len() {
+        if old < old_keys.len() && (deleted[old] || new == new_keys.len()) {
+            ops.push(LineOp::Delete(old));
+            old += 1;
+        } else if new < new_keys.len() && (inserted[new] || old == old_keys.len()) {
+            ops.push(LineOp::Insert(new));
+            new += 1;
+        } else {
+            ops.push(LineOp::Keep { old, new });
+            old += 1;
+            new += 1;
+        }
+    }
+    ops
+}
+
+/// Find the ranges of operations that make up each hunk of the patch.
+///
+/// Each hunk has up to `context_lines` unchanged lines before and after its changes. Changes that
+/// are close enough for their context to overlap or touch are part of the same hunk.
+fn change_windows(ops: &[LineOp], context_lines: usize) -> Vec<std::ops::Range<usize>> {
+    let mut windows: Vec<std::ops::Range<usize>> = Vec::new();
+    for (idx, op) in ops.iter().enumerate() {
+        if matches!(op, LineOp::Keep { .. }) {
+            continue;
+        }
+        let start = idx.saturating_sub(context_lines);
+        let end = (idx + 1 + context_lines).min(ops.len());
+        match windows.last_mut() {
+            Some(window) if start <= window.end => window.end = end,
+            _ => windows.push(start..end),
+        }
+    }
+    windows
+}
+
+/// Get the start and length of the range of lines that a hunk covers in one of the documents,
+/// for the hunk header.
+///
+/// `before` has the operations before the hunk and `ops` has the operations in the hunk. The
+/// start is 1-based, except for an empty range, where it's the line that the range comes after.
+fn line_range(before: &[LineOp], ops: &[LineOp], old: bool) -> (usize, usize) {
+    let count_lines = |ops: &[LineOp]| {
+        ops.iter()
+            .filter(|op| match op {
+                LineOp::Keep { .. } => true,
+                LineOp::Delete(_) => old,
+                LineOp::Insert(_) => !old,
+            })
+            .count()
+    };
+    let preceding = count_lines(before);
+    let count = count_lines(ops);
+    if count == 0 {
+        (preceding, 0)
+    } else {
+        (preceding + 1, count)
+    }
+}
+
+/// Get the path of a document in the patch header.
+fn patch_path(prefix: &str, filename: &str) -> String {
+    if filename == NULL_PATH {
+        filename.into()
+    } else {
+        format!("{prefix}{filename}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{Hunk, Line, RichHunks};
+    use crate::render::DocumentDiffData;
+    use pretty_assertions::assert_eq;
+
+    /// Render a patch for two documents, where the given lines have semantic edits.
+    fn render(old_text: &str, new_text: &str, old_edits: &[usize], new_edits: &[usize]) -> String {
+        let mut hunks = Vec::new();
+        if !old_edits.is_empty() {
+            hunks.push(RichHunk::Old(Hunk(
+                old_edits.iter().copied().map(Line::new).collect(),
+            )));
+        }
+        if !new_edits.is_empty() {
+            hunks.push(RichHunk::New(Hunk(
+                new_edits.iter().copied().map(Line::new).collect(),
+            )));
+        }
+        let data = DisplayData {
+            hunks: RichHunks(hunks),
+            old: DocumentDiffData {
+                filename: "a.rs",
+                text: old_text,
+                language: "rust",
+            },
+            new: DocumentDiffData {
+                filename: "a.rs",
+                text: new_text,
+                language: "rust",
+            },
+            moves: Vec::new(),
+            symbols: Vec::new(),
+            groups: Vec::new(),
+        };
+        let patch = Patch {
+            context_lines: 1,
+            ..Patch::default()
+        };
+        let mut output = Vec::new();
+        patch.render(&mut output, &data, None).unwrap();
+        String::from_utf8(output).unwrap()
+    }
+
+    #[test]
+    fn semantic_edits() {
+        let old = "a\nb\nc\nd\ne\nf\n";
+        let new = "a\nB\nc\nd\ne\nf\ng\n";
+        let expected = "--- a/a.rs
++++ b/a.rs
+@@ -1,3 +1,3 @@
+ a
+-b
++B
+ c
+@@ -6,1 +6,2 @@
+ f
++g
+";
+        assert_eq!(render(old, new, &[1], &[1, 6]), expected);
+    }
+
+    #[test]
+    fn whitespace_changes_are_kept() {
+        // The semantic diff ignores the indentation, but the patch has to reproduce it
+        let expected = "--- a/a.rs
++++ b/a.rs
+@@ -1,1 +1,1 @@
+-a
++  a
+";
+        assert_eq!(render("a\n", "  a\n", &[], &[]), expected);
+    }
+
+    #[test]
+    fn missing_newline() {
+        let expected = "--- a/a.rs
++++ b/a.rs
+@@ -1,1 +1,1 @@
+-a
+\\ No newline at end of file
++a
+";
+        assert_eq!(render("a", "a\n", &[], &[]), expected);
+    }
+
+    #[test]
+    fn no_changes() {
+        assert_eq!(render("a\nb\n", "a\nb\n", &[], &[]), "");
+    }
+
+    #[test]
+    fn empty_ranges() {
+        let expected = "--- a/a.rs
++++ b/a.rs
+@@ -0,0 +1,1 @@
++a
+";
+        assert_eq!(render("", "a\n", &[], &[0]), expected);
+    }
+
+    #[test]
+    fn test_patch_path() {
+        assert_eq!(patch_path("a/", "src/main.rs"), "a/src/main.rs");
+        assert_eq!(patch_path("a/", NULL_PATH), NULL_PATH);
+    }
+}
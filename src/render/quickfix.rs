@@ -0,0 +1,111 @@
+use crate::diff::RichHunk;
+use crate::render::{annotations::start_location, DisplayData, Renderer};
+use anyhow::Result;
+use console::Term;
+use serde::{Deserialize, Serialize};
+use std::io::Write;
+
+/// A renderer that writes a `file:line:col: message` record for each change, which editors can
+/// use to jump to the changes, like Vim's quickfix list and Emacs' compilation mode.
+///
+/// Additions refer to the new document and deletions refer to the old document. The message is
+/// whether the code was added or deleted, followed by the text of the line.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct Quickfix {
+    /// Whether to write a record for every changed line or for every hunk
+    pub granularity: Granularity,
+}
+
+/// How many records are written for the changes
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Granularity {
+    /// A record for every line with edits
+    #[default]
+    Line,
+    /// A record for every hunk, at its first line
+    Hunk,
+}
+
+impl Renderer for Quickfix {
+    fn render(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        _term_info: Option<&Term>,
+    ) -> Result<()> {
+        let old_lines: Vec<_> = data.old.text.lines().collect();
+        let new_lines: Vec<_> = data.new.text.lines().collect();
+
+        for hunk_idx in data.hunk_groups().concat() {
+            let (lines, path, action) = match &data.hunks.0[hunk_idx] {
+                RichHunk::Old(_) => (&old_lines, data.old.filename, "deleted"),
+                RichHunk::New(_) => (&new_lines, data.new.filename, "added"),
+            };
+            let hunk_lines = &data.hunks.0[hunk_idx].as_ref().0;
+            let record_lines = match self.granularity {
+                Granularity::Line => &hunk_lines[..],
+                Granularity::Hunk => &hunk_lines[..hunk_lines.len().min(1)],
+            };
+            for line in record_lines {
+                let location = start_location(line, lines);
+                let text = lines.get(line.line_index).copied().unwrap_or_default();
+                writeln!(
+                    writer,
+                    "{path}:{}:{}: {}",
+                    location.line,
+                    location.column,
+                    format!("{action} {}", text.trim()).trim_end()
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{Hunk, Line, RichHunks};
+    use crate::render::DocumentDiffData;
+    use pretty_assertions::assert_eq;
+    use test_case::test_case;
+
+    #[test_case(Granularity::Line, "\
+a.rs:2:1: deleted a();
+b.rs:2:1: added b();
+b.rs:3:1: added c();
+b.rs:4:1: added
+" ; "line")]
+    #[test_case(Granularity::Hunk, "\
+a.rs:2:1: deleted a();
+b.rs:2:1: added b();
+" ; "hunk")]
+    fn render_records(granularity: Granularity, expected: &str) {
+        let data = DisplayData {
+            hunks: RichHunks(vec![
+                RichHunk::Old(Hunk(vec![Line::new(1)])),
+                RichHunk::New(Hunk(vec![Line::new(1), Line::new(2), Line::new(3)])),
+            ]),
+            old: DocumentDiffData {
+                filename: "a.rs",
+                text: "fn main() {\n    a();\n}\n",
+                language: "rust",
+            },
+            new: DocumentDiffData {
+                filename: "b.rs",
+                text: "fn main() {\n    b();\n    c();\n\n}\n",
+                language: "rust",
+            },
+            moves: Vec::new(),
+            symbols: Vec::new(),
+            groups: vec![vec![0, 1]],
+        };
+        let mut output = Vec::new();
+        Quickfix { granularity }
+            .render(&mut output, &data, None)
+            .unwrap();
+        assert_eq!(String::from_utf8(output).unwrap(), expected);
+    }
+}
@@ -0,0 +1,233 @@
+use crate::render::{
+    annotations::{annotations, AnnotationLevel, ChangeKind},
+    DisplayData, RenderState, Renderer,
+};
+use anyhow::Result;
+use console::Term;
+use serde::{Deserialize, Serialize};
+use serde_json::json;
+use std::io::Write;
+
+/// The schema of the SARIF logs we write
+const SCHEMA: &str = "https://json.schemastore.org/sarif-2.1.0.json";
+
+/// A renderer that writes a SARIF log, which code scanning tools like GitHub's can display on the
+/// lines of a pull request.
+///
+/// Each hunk is a result whose region covers the edited entries. Every diff is written into a
+/// single log with one run, so the output of a directory diff is still one document.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Default)]
+#[serde(rename_all = "kebab-case")]
+pub struct Sarif {
+    /// The level of the results
+    pub level: AnnotationLevel,
+}
+
+impl Renderer for Sarif {
+    fn render(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        term_info: Option<&Term>,
+    ) -> Result<()> {
+        // On its own, a diff is written as a complete log
+        let mut state = RenderState::default();
+        self.begin(writer, &mut state)?;
+        self.render_part(writer, data, term_info, &mut state)?;
+        self.end(writer, &mut state)
+    }
+
+    fn render_part(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        _term_info: Option<&Term>,
+        state: &mut RenderState,
+    ) -> Result<()> {
+        let level = match self.level {
+            AnnotationLevel::Notice => "note",
+            AnnotationLevel::Warning => "warning",
+            AnnotationLevel::Error => "error",
+        };
+        for annotation in annotations(data) {
+            let result = json!({
+                "ruleId": annotation.kind.id(),
+                "level": level,
+                "message": { "text": annotation.message },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": annotation.path },
+                        "region": {
+                            "startLine": annotation.start.line,
+                            "startColumn": annotation.start.column,
+                            "endLine": annotation.end.line,
+                            "endColumn": annotation.end.column,
+                        },
+                    },
+                }],
+            });
+            if state.items_written > 0 {
+                writeln!(writer, ",")?;
+            }
+            write!(writer, "{result}")?;
+            state.items_written += 1;
+        }
+        Ok(())
+    }
+
+    fn begin(&self, writer: &mut dyn Write, _state: &mut RenderState) -> Result<()> {
+        let rules: Vec<_> = [ChangeKind::Addition, ChangeKind::Deletion]
+            .into_iter()
+            .map(|kind| {
+                let description = match kind {
+                    ChangeKind::Addition => "Code was added",
+                    ChangeKind::Deletion => "Code was deleted",
+                };
+                json!({
+                    "id": kind.id(),
+                    "shortDescription": { "text": description },
+                })
+            })
+            .collect();
+        let tool = json!({
+            "driver": {
+                "name": env!("CARGO_PKG_NAME"),
+                "version": env!("CARGO_PKG_VERSION"),
+                "informationUri": env!("CARGO_PKG_HOMEPAGE"),
+                "rules": rules,
+            },
+        });
+        // The results are written as each diff is rendered, so the log is written by hand rather
+        // than serialized in one go
+        writeln!(
+            writer,
+            "{{\"$schema\":\"{SCHEMA}\",\"version\":\"2.1.0\",\"runs\":[{{\"tool\":{tool},\"columnKind\":\"unicodeCodePoints\",\"results\":["
+        )?;
+        Ok(())
+    }
+
+    fn end(&self, writer: &mut dyn Write, state: &mut RenderState) -> Result<()> {
+        if state.items_written > 0 {
+            writeln!(writer)?;
+        }
+        writeln!(writer, "]}}]}}")?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::{Hunk, Line, RichHunk, RichHunks};
+    use crate::render::DocumentDiffData;
+    use pretty_assertions::assert_eq;
+    use serde_json::Value;
+
+    #[test]
+    fn render_log() {
+        let data = DisplayData {
+            hunks: RichHunks(vec![
+                RichHunk::Old(Hunk(vec![Line::new(1)])),
+                RichHunk::New(Hunk(vec![Line::new(1)])),
+            ]),
+            old: DocumentDiffData {
+                filename: "a.rs",
+                text: "fn main() {\n    a();\n}\n",
+                language: "rust",
+            },
+            new: DocumentDiffData {
+                filename: "b.rs",
+                text: "fn main() {\n    bé();\n}\n",
+                language: "rust",
+            },
+            moves: Vec::new(),
+            symbols: Vec::new(),
+            groups: vec![vec![0, 1]],
+        };
+        let renderer = Sarif {
+            level: AnnotationLevel::Error,
+        };
+        let mut output = Vec::new();
+        let mut state = RenderState::default();
+        renderer.begin(&mut output, &mut state).unwrap();
+        // Rendering two diffs still writes a single log
+        renderer
+            .render_part(&mut output, &data, None, &mut state)
+            .unwrap();
+        renderer
+            .render_part(&mut output, &data, None, &mut state)
+            .unwrap();
+        renderer.end(&mut output, &mut state).unwrap();
+        // The renderer's configuration doesn't change while rendering
+        assert_eq!(
+            renderer,
+            Sarif {
+                level: AnnotationLevel::Error
+            }
+        );
+
+        let log: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(log["version"], "2.1.0");
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 4);
+        assert_eq!(
+            results[1],
+            json!({
+                "ruleId": "addition",
+                "level": "error",
+                "message": { "text": "Added code" },
+                "locations": [{
+                    "physicalLocation": {
+                        "artifactLocation": { "uri": "b.rs" },
+                        "region": {
+                            "startLine": 2,
+                            "startColumn": 1,
+                            "endLine": 2,
+                            "endColumn": 10,
+                        },
+                    },
+                }],
+            })
+        );
+    }
+
+    #[test]
+    fn render_empty_log() {
+        let renderer = Sarif::default();
+        let mut output = Vec::new();
+        let mut state = RenderState::default();
+        renderer.begin(&mut output, &mut state).unwrap();
+        renderer.end(&mut output, &mut state).unwrap();
+        let log: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(log["runs"][0]["results"], json!([]));
+    }
+
+    /// Rendering a single diff on its own writes a complete log.
+    #[test]
+    fn render_standalone_log() {
+        let data = DisplayData {
+            hunks: RichHunks(vec![RichHunk::New(Hunk(vec![Line::new(0)]))]),
+            old: DocumentDiffData {
+                filename: "a.rs",
+                text: "",
+                language: "rust",
+            },
+            new: DocumentDiffData {
+                filename: "b.rs",
+                text: "fn main() {}\n",
+                language: "rust",
+            },
+            moves: Vec::new(),
+            symbols: Vec::new(),
+            groups: vec![vec![0]],
+        };
+        let mut output = Vec::new();
+        Sarif::default().render(&mut output, &data, None).unwrap();
+        let log: Value = serde_json::from_slice(&output).unwrap();
+        assert_eq!(log["$schema"], SCHEMA);
+        assert_eq!(log["version"], "2.1.0");
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0]["ruleId"], "addition");
+    }
+}
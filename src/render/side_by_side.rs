@@ -0,0 +1,413 @@
+use crate::diff::{Hunk, RichHunk};
+use crate::render::{
+    line_segments, unified::TextStyle, unified::Unified, DisplayData, EmphasizedStyle,
+    RegularStyle, Renderer,
+};
+use anyhow::Result;
+use console::{measure_text_width, Term};
+use log::{debug, info};
+use serde::{Deserialize, Serialize};
+use std::{cmp::max, io::Write};
+use unicode_segmentation::UnicodeSegmentation;
+
+/// The terminal width to use if it can't be determined from the terminal
+const DEFAULT_TERM_WIDTH: usize = 80;
+
+/// The number of spaces a tab is expanded to
+const TAB_WIDTH: usize = 4;
+
+/// The ascii separator used after the diff title
+const TITLE_SEPARATOR: &str = "=";
+
+/// The ascii separator used after the hunk title
+const HUNK_TITLE_SEPARATOR: &str = "-";
+
+/// The marker used to indicate that a line was truncated
+const TRUNCATION_MARKER: &str = "…";
+
+/// A renderer that displays the old and new documents in two columns.
+///
+/// Corresponding hunks from the old and new documents are aligned next to each other, with the old
+/// document on the left and the new document on the right.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct SideBySide {
+    pub addition: TextStyle,
+    pub deletion: TextStyle,
+    /// How to handle lines that are wider than their column
+    pub overflow: Overflow,
+    /// The string that separates the two columns
+    pub separator: String,
+}
+
+/// The strategies for displaying lines that don't fit in a column
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum Overflow {
+    /// Continue the line on the next row of the column
+    #[default]
+    Wrap,
+    /// Cut the line off at the edge of the column
+    Truncate,
+}
+
+impl Default for SideBySide {
+    fn default() -> Self {
+        let Unified {
+            addition, deletion, ..
+        } = Unified::default();
+        SideBySide {
+            addition,
+            deletion,
+            overflow: Overflow::default(),
+            separator: " | ".into(),
+        }
+    }
+}
+
+/// A segment of text in a column and whether it should be emphasized
+type Segment = (String, bool);
+
+/// A single row of text in a column
+type ColumnRow = Vec<Segment>;
+
+/// The styles that apply to a column
+struct ColumnStyle<'a> {
+    regular: RegularStyle,
+    emphasis: EmphasizedStyle,
+    prefix: &'a str,
+}
+
+impl<'a> From<&'a TextStyle> for ColumnStyle<'a> {
+    fn from(style: &'a TextStyle) -> Self {
+        Self {
+            regular: style.into(),
+            emphasis: style.into(),
+            prefix: &style.prefix,
+        }
+    }
+}
+
+impl Renderer for SideBySide {
+    fn render(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        term_info: Option<&Term>,
+    ) -> Result<()> {
+        let DisplayData {
+            hunks, old, new, ..
+        } = &data;
+        let old_style = ColumnStyle::from(&self.deletion);
+        let new_style = ColumnStyle::from(&self.addition);
+        let old_lines: Vec<_> = old.text.lines().collect();
+        let new_lines: Vec<_> = new.text.lines().collect();
+
+        let term_width = term_info
+            .and_then(Term::size_checked)
+            .map_or(DEFAULT_TERM_WIDTH, |(_height, width)| width.into());
+        // Each column needs to be at least wide enough to display a single character
+        let column_width = max(
+            term_width.saturating_sub(measure_text_width(&self.separator)) / 2,
+            1,
+        );
+        info!("Using a column width of {column_width} for a terminal width of {term_width}");
+
+        let title = (
+            vec![(old.filename.to_string(), false)],
+            vec![(new.filename.to_string(), false)],
+        );
+        self.print_rows(writer, &[title], column_width, &old_style, &new_style)?;
+        writeln!(writer, "{}", TITLE_SEPARATOR.repeat(term_width))?;
+
+        for (old_hunk, new_hunk) in pair_hunks(&hunks.0) {
+            debug!("Printing hunk pair");
+            let old_rows = self.hunk_rows(old_hunk, &old_lines, &old_style, column_width);
+            let new_rows = self.hunk_rows(new_hunk, &new_lines, &new_style, column_width);
+            let num_rows = max(old_rows.len(), new_rows.len());
+            let rows: Vec<_> = (0..num_rows)
+                .map(|i| {
+                    (
+                        old_rows.get(i).cloned().unwrap_or_default(),
+                        new_rows.get(i).cloned().unwrap_or_default(),
+                    )
+                })
+                .collect();
+            writeln!(writer)?;
+            self.print_rows(writer, &rows, column_width, &old_style, &new_style)?;
+        }
+        Ok(())
+    }
+}
+
+impl SideBySide {
+    /// Generate the rows for one column of a hunk, including the hunk title.
+    ///
+    /// If the hunk is missing, this returns an empty vector.
+    fn hunk_rows(
+        &self,
+        hunk: Option<&Hunk>,
+        lines: &[&str],
+        style: &ColumnStyle,
+        column_width: usize,
+    ) -> Vec<ColumnRow> {
+        let Some(hunk) = hunk else {
+            return Vec::new();
+        };
+        let first_line = hunk.first_line().unwrap();
+        let last_line = hunk.last_line().unwrap();
+        // We don't need to display a range `x - x:` since `x:` is terser and clearer
+        let title = if first_line == last_line {
+            format!("{first_line}:")
+        } else {
+            format!("{first_line} - {last_line}:")
+        };
+        let separator = HUNK_TITLE_SEPARATOR.repeat(measure_text_width(&title));
+        let mut rows = vec![vec![(title, false)], vec![(separator, false)]];
+
+        for line in &hunk.0 {
+            let Some(text) = lines.get(line.line_index) else {
+                debug!(
+                    "Received invalid line index {}. Skipping this line.",
+                    line.line_index
+                );
+                continue;
+            };
+            let mut segments = vec![(style.prefix.to_string(), false)];
+            segments.extend(
+                line_segments(text, line)
+                    .into_iter()
+                    .map(|(text, emphasized)| {
+                        (text.replace('\t', &" ".repeat(TAB_WIDTH)), emphasized)
+                    }),
+            );
+            rows.extend(Self::layout(
+                &segments,
+                measure_text_width(style.prefix),
+                column_width,
+                self.overflow,
+            ));
+        }
+        rows
+    }
+
+    /// Fit a line of text into a column, according to the overflow strategy.
+    ///
+    /// This returns the rows that the line occupies in the column. Rows that continue a wrapped
+    /// line are indented by `indent` so they line up with the text after the prefix.
+    fn layout(
+        segments: &[Segment],
+        indent: usize,
+        column_width: usize,
+        overflow: Overflow,
+    ) -> Vec<ColumnRow> {
+        // Reserve room for the truncation marker so the column width isn't exceeded
+        let max_width = match overflow {
+            Overflow::Wrap => column_width,
+            Overflow::Truncate => {
+                column_width.saturating_sub(measure_text_width(TRUNCATION_MARKER))
+            }
+        };
+        // If the indent takes up the entire column, there's no room left to continue a line
+        let indent = if indent >= max_width { 0 } else { indent };
+        let mut rows: Vec<ColumnRow> = vec![Vec::new()];
+        let mut row_width = 0;
+
+        for (text, emphasized) in segments {
+            for grapheme in text.graphemes(true) {
+                let grapheme_width = measure_text_width(grapheme);
+
+                if row_width + grapheme_width > max_width && row_width > 0 {
+                    if overflow == Overflow::Truncate {
+                        rows.last_mut()
+                            .unwrap()
+                            .push((TRUNCATION_MARKER.into(), false));
+                        return rows;
+                    }
+                    rows.push(vec![(" ".repeat(indent), false)]);
+                    row_width = indent;
+                }
+                let row = rows.last_mut().unwrap();
+
+                // Merge graphemes into the last segment if it has the same emphasis so we don't
+                // have to apply a style for every grapheme
+                match row.last_mut() {
+                    Some((last_text, last_emphasized)) if last_emphasized == emphasized => {
+                        last_text.push_str(grapheme);
+                    }
+                    _ => row.push((grapheme.to_string(), *emphasized)),
+                }
+                row_width += grapheme_width;
+            }
+        }
+        rows
+    }
+
+    /// Print rows of text in two columns, separated by the column separator
+    fn print_rows(
+        &self,
+        writer: &mut dyn Write,
+        rows: &[(ColumnRow, ColumnRow)],
+        column_width: usize,
+        old_style: &ColumnStyle,
+        new_style: &ColumnStyle,
+    ) -> std::io::Result<()> {
+        for (old_row, new_row) in rows {
+            // Rows that are wider than the column (which may happen with titles) get wrapped
+            let old_rows = Self::layout(old_row, 0, column_width, Overflow::Wrap);
+            let new_rows = Self::layout(new_row, 0, column_width, Overflow::Wrap);
+
+            for i in 0..max(old_rows.len(), new_rows.len()) {
+                let empty = Vec::new();
+                let old_row = old_rows.get(i).unwrap_or(&empty);
+                let new_row = new_rows.get(i).unwrap_or(&empty);
+                let old_width = print_segments(writer, old_row, old_style)?;
+                write!(
+                    writer,
+                    "{}{}",
+                    " ".repeat(column_width.saturating_sub(old_width)),
+                    self.separator
+                )?;
+                print_segments(writer, new_row, new_style)?;
+                writeln!(writer)?;
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Print styled segments and return the width of the text that was printed
+fn print_segments(
+    writer: &mut dyn Write,
+    segments: &[Segment],
+    style: &ColumnStyle,
+) -> std::io::Result<usize> {
+    let mut width = 0;
+    for (text, emphasized) in segments {
+        let style = if *emphasized {
+            &style.emphasis.0
+        } else {
+            &style.regular.0
+        };
+        write!(writer, "{}", style.apply_to(text))?;
+        width += measure_text_width(text);
+    }
+    Ok(width)
+}
+
+/// Pair up the hunks from the old and new documents so they can be displayed next to each other.
+///
+/// A hunk is paired with the hunk that directly follows it if the two hunks come from different
+/// documents. Hunks that don't have a partner are paired with `None`.
+fn pair_hunks<'a, 'b>(
+    hunks: &'b [RichHunk<'a>],
+) -> Vec<(Option<&'b Hunk<'a>>, Option<&'b Hunk<'a>>)> {
+    let mut pairs = Vec::new();
+    let mut old: Option<&Hunk> = None;
+    let mut new: Option<&Hunk> = None;
+
+    for hunk_wrapper in hunks {
+        // If there's already a pending hunk for this document, it doesn't have a partner
+        let slot_taken = match hunk_wrapper {
+            RichHunk::Old(_) => old.is_some(),
+            RichHunk::New(_) => new.is_some(),
+        };
+        if slot_taken {
+            pairs.push((old.take(), new.take()));
+        }
+        match hunk_wrapper {
+            RichHunk::Old(hunk) => old = Some(hunk),
+            RichHunk::New(hunk) => new = Some(hunk),
+        }
+        if old.is_some() && new.is_some() {
+            pairs.push((old.take(), new.take()));
+        }
+    }
+    if old.is_some() || new.is_some() {
+        pairs.push((old, new));
+    }
+    pairs
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::Line;
+    use pretty_assertions::assert_eq;
+
+    fn hunk(first: usize, last: usize) -> Hunk<'static> {
+        Hunk((first..=last).map(Line::new).collect())
+    }
+
+    fn first_lines(
+        pairs: &[(Option<&Hunk>, Option<&Hunk>)],
+    ) -> Vec<(Option<usize>, Option<usize>)> {
+        pairs
+            .iter()
+            .map(|(old, new)| {
+                (
+                    old.and_then(Hunk::first_line),
+                    new.and_then(Hunk::first_line),
+                )
+            })
+            .collect()
+    }
+
+    #[test]
+    fn pair_hunks_alternating() {
+        let hunks = vec![
+            RichHunk::Old(hunk(1, 2)),
+            RichHunk::New(hunk(1, 1)),
+            RichHunk::New(hunk(5, 6)),
+            RichHunk::Old(hunk(8, 8)),
+        ];
+        let pairs = pair_hunks(&hunks);
+        assert_eq!(
+            first_lines(&pairs),
+            vec![(Some(1), Some(1)), (Some(8), Some(5))]
+        );
+    }
+
+    #[test]
+    fn pair_hunks_unmatched() {
+        let hunks = vec![
+            RichHunk::Old(hunk(1, 2)),
+            RichHunk::Old(hunk(4, 4)),
+            RichHunk::New(hunk(3, 3)),
+            RichHunk::New(hunk(7, 7)),
+        ];
+        let pairs = pair_hunks(&hunks);
+        assert_eq!(
+            first_lines(&pairs),
+            vec![(Some(1), None), (Some(4), Some(3)), (None, Some(7))]
+        );
+    }
+
+    #[test]
+    fn layout_wrap() {
+        let segments = vec![("- ".to_string(), false), ("abcdefgh".to_string(), true)];
+        let rows = SideBySide::layout(&segments, 2, 5, Overflow::Wrap);
+        assert_eq!(
+            rows,
+            vec![
+                vec![("- ".to_string(), false), ("abc".to_string(), true)],
+                vec![("  ".to_string(), false), ("def".to_string(), true)],
+                vec![("  ".to_string(), false), ("gh".to_string(), true)],
+            ]
+        );
+    }
+
+    #[test]
+    fn layout_truncate() {
+        let segments = vec![("- ".to_string(), false), ("abcdefgh".to_string(), true)];
+        let rows = SideBySide::layout(&segments, 2, 5, Overflow::Truncate);
+        assert_eq!(
+            rows,
+            vec![vec![
+                ("- ".to_string(), false),
+                ("ab".to_string(), true),
+                (TRUNCATION_MARKER.to_string(), false),
+            ]]
+        );
+    }
+}
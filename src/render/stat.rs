@@ -0,0 +1,316 @@
+use crate::diff::RichHunk;
+use crate::render::{ColorDef, DisplayData, Renderer};
+use anyhow::Result;
+use console::{measure_text_width, Color, Style, Term};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    io::Write,
+};
+
+/// A renderer that summarizes a diff, like `git diff --stat`.
+///
+/// Rather than displaying the hunks, this counts the entries that were added and deleted, the lines
+/// they're on and the hunks they make up. The changes can also be broken down by the kind of
+/// syntax node they come from, so you can tell whether a diff changed identifiers, string literals
+/// or comments.
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone)]
+#[serde(rename_all = "kebab-case")]
+pub struct Stat {
+    /// The color for the number of additions
+    #[serde(with = "ColorDef")]
+    pub addition: Color,
+    /// The color for the number of deletions
+    #[serde(with = "ColorDef")]
+    pub deletion: Color,
+    /// Whether to break down the changes by the kind of syntax node
+    pub show_kinds: bool,
+    /// The format to write the statistics in
+    pub format: StatFormat,
+}
+
+/// The formats the statistics can be written in
+#[derive(Debug, Serialize, Deserialize, Eq, PartialEq, Clone, Copy, Default)]
+#[serde(rename_all = "snake_case")]
+pub enum StatFormat {
+    /// A human readable summary
+    #[default]
+    Text,
+    /// A JSON object for each pair of documents, which can be consumed by other tools
+    Json,
+}
+
+impl Default for Stat {
+    fn default() -> Self {
+        Stat {
+            addition: Color::Green,
+            deletion: Color::Red,
+            show_kinds: true,
+            format: StatFormat::default(),
+        }
+    }
+}
+
+/// Statistics about the diff of two documents
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Default)]
+pub struct DiffStat<'a> {
+    /// The filename of the old document
+    pub old_filename: &'a str,
+    /// The filename of the new document
+    pub new_filename: &'a str,
+    /// The number of hunks in both documents
+    pub hunks: usize,
+    /// The number of entries that were added
+    pub added_entries: usize,
+    /// The number of entries that were deleted
+    pub deleted_entries: usize,
+    /// The number of lines in the new document with added entries
+    pub added_lines: usize,
+    /// The number of lines in the old document with deleted entries
+    pub deleted_lines: usize,
+    /// The number of blocks of code that were moved
+    pub moves: usize,
+    /// The changes for each kind of syntax node, ordered from the most to least changes
+    pub kinds: Vec<KindStat<'a>>,
+}
+
+/// The changes for a kind of syntax node
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct KindStat<'a> {
+    /// The name of the node kind, like `identifier`
+    pub kind: &'a str,
+    /// The numeric ID of the node kind, which is specific to the grammar
+    pub kind_id: u16,
+    /// The number of entries of this kind that were added
+    pub additions: usize,
+    /// The number of entries of this kind that were deleted
+    pub deletions: usize,
+}
+
+impl<'a> From<&DisplayData<'a>> for DiffStat<'a> {
+    fn from(data: &DisplayData<'a>) -> Self {
+        let mut stat = DiffStat {
+            old_filename: data.old.filename,
+            new_filename: data.new.filename,
+            hunks: data.hunks.0.len(),
+            moves: data.moves.len(),
+            ..DiffStat::default()
+        };
+        let mut added_lines = BTreeSet::new();
+        let mut deleted_lines = BTreeSet::new();
+        let mut kinds: BTreeMap<u16, KindStat> = BTreeMap::new();
+
+        for hunk in &data.hunks.0 {
+            let (lines, is_addition) = match hunk {
+                RichHunk::Old(_) => (&mut deleted_lines, false),
+                RichHunk::New(_) => (&mut added_lines, true),
+            };
+            for line in &hunk.as_ref().0 {
+                lines.insert(line.line_index);
+            }
+            for entry in hunk.as_ref().entries() {
+                let kind = kinds.entry(entry.kind_id).or_insert_with(|| KindStat {
+                    kind: entry.reference.kind(),
+                    kind_id: entry.kind_id,
+                    additions: 0,
+                    deletions: 0,
+                });
+                if is_addition {
+                    kind.additions += 1;
+                    stat.added_entries += 1;
+                } else {
+                    kind.deletions += 1;
+                    stat.deleted_entries += 1;
+                }
+            }
+        }
+        stat.added_lines = added_lines.len();
+        stat.deleted_lines = deleted_lines.len();
+        stat.kinds = kinds.into_values().collect();
+        stat.kinds.sort_by(|a, b| {
+            (b.additions + b.deletions)
+                .cmp(&(a.additions + a.deletions))
+                .then_with(|| a.kind.cmp(b.kind))
+        });
+        stat
+    }
+}
+
+impl Renderer for Stat {
+    fn render(
+        &self,
+        writer: &mut dyn Write,
+        data: &DisplayData,
+        _term_info: Option<&Term>,
+    ) -> Result<()> {
+        let mut stat = DiffStat::from(data);
+        if !self.show_kinds {
+            stat.kinds.clear();
+        }
+        match self.format {
+            StatFormat::Text => self.write_text(writer, &stat)?,
+            // End with a newline so the output of multiple diffs can be read as JSON lines
+            StatFormat::Json => writeln!(writer, "{}", serde_json::to_string(&stat)?)?,
+        }
+        Ok(())
+    }
+}
+
+impl Stat {
+    /// Write the statistics as a human readable summary.
+    fn write_text(&self, writer: &mut dyn Write, stat: &DiffStat) -> Result<()> {
+        writeln!(writer, "{} -> {}", stat.old_filename, stat.new_filename)?;
+        let mut summary = vec![
+            plural(stat.hunks, "hunk"),
+            format!(
+                "{} changed ({}, {})",
+                plural(stat.added_lines + stat.deleted_lines, "line"),
+                self.additions(stat.added_lines),
+                self.deletions(stat.deleted_lines),
+            ),
+            format!(
+                "{} changed ({}, {})",
+                plural(stat.added_entries + stat.deleted_entries, "entry"),
+                self.additions(stat.added_entries),
+                self.deletions(stat.deleted_entries),
+            ),
+        ];
+        if stat.moves > 0 {
+            summary.push(format!("{} moved", plural(stat.moves, "block")));
+        }
+        writeln!(writer, " {}", summary.join(", "))?;
+
+        let kind_width = stat
+            .kinds
+            .iter()
+            .map(|kind| measure_text_width(kind.kind))
+            .max()
+            .unwrap_or_default();
+        for kind in &stat.kinds {
+            writeln!(
+                writer,
+                "   {:kind_width$} {} {}",
+                kind.kind,
+                self.additions(kind.additions),
+                self.deletions(kind.deletions),
+            )?;
+        }
+        Ok(())
+    }
+
+    /// Format a number of additions, like `+3`.
+    fn additions(&self, count: usize) -> String {
+        Style::new()
+            .fg(self.addition)
+            .apply_to(format!("+{count}"))
+            .to_string()
+    }
+
+    /// Format a number of deletions, like `-3`.
+    fn deletions(&self, count: usize) -> String {
+        Style::new()
+            .fg(self.deletion)
+            .apply_to(format!("-{count}"))
+            .to_string()
+    }
+}
+
+/// Format a count with the singular or plural form of a noun, like `1 hunk` or `2 hunks`.
+fn plural(count: usize, noun: &str) -> String {
+    match (count, noun.strip_suffix('y')) {
+        (1, _) => format!("{count} {noun}"),
+        (_, Some(stem)) => format!("{count} {stem}ies"),
+        (_, None) => format!("{count} {noun}s"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq;
+    use test_case::test_case;
+
+    #[test_case(0, "hunk", "0 hunks")]
+    #[test_case(1, "hunk", "1 hunk")]
+    #[test_case(2, "entry", "2 entries")]
+    #[test_case(1, "entry", "1 entry")]
+    fn test_plural(count: usize, noun: &str, expected: &str) {
+        assert_eq!(plural(count, noun), expected);
+    }
+
+    #[test]
+    fn write_text() {
+        let stat = DiffStat {
+            old_filename: "a.rs",
+            new_filename: "b.rs",
+            hunks: 2,
+            added_entries: 3,
+            deleted_entries: 1,
+            added_lines: 2,
+            deleted_lines: 1,
+            moves: 1,
+            kinds: vec![
+                KindStat {
+                    kind: "identifier",
+                    kind_id: 1,
+                    additions: 2,
+                    deletions: 1,
+                },
+                KindStat {
+                    kind: "string",
+                    kind_id: 2,
+                    additions: 1,
+                    deletions: 0,
+                },
+            ],
+        };
+        let mut output = Vec::new();
+        Stat::default().write_text(&mut output, &stat).unwrap();
+        let expected = "a.rs -> b.rs
+ 2 hunks, 3 lines changed (+2, -1), 4 entries changed (+3, -1), 1 block moved
+   identifier +2 -1
+   string     +1 -0
+";
+        let output = String::from_utf8(output).unwrap();
+        assert_eq!(console::strip_ansi_codes(&output), expected);
+    }
+
+    // NOTE: this has to be gated behind the 'static-grammar-libs' cargo feature, otherwise the
+    // grammar isn't available to parse the documents.
+    #[cfg(feature = "static-grammar-libs")]
+    #[test]
+    fn diff_stat_from_display_data() {
+        use crate::{Differ, Source};
+
+        let source = |name: &str, text: &str| Source::Text {
+            name: name.into(),
+            text: text.into(),
+        };
+        let session = Differ::default()
+            .diff(
+                source("a.rs", "fn main() {\n    let x = \"a\";\n}\n"),
+                source("b.rs", "fn main() {\n    let y = \"b\";\n    z();\n}\n"),
+            )
+            .unwrap();
+        let stat = session
+            .with_display_data(|data| {
+                let stat = DiffStat::from(data);
+                (
+                    stat.added_lines,
+                    stat.deleted_lines,
+                    stat.added_entries,
+                    stat.deleted_entries,
+                    stat.kinds
+                        .iter()
+                        .map(|kind| (kind.kind.to_string(), kind.additions, kind.deletions))
+                        .collect::<Vec<_>>(),
+                )
+            })
+            .unwrap();
+        let (added_lines, deleted_lines, added_entries, deleted_entries, kinds) = stat;
+        assert_eq!((added_lines, deleted_lines), (2, 1));
+        // `x` and `a` were deleted, `y`, `b`, `z`, `(`, `)` and `;` were added
+        assert_eq!((added_entries, deleted_entries), (6, 2));
+        assert_eq!(kinds[0], ("identifier".to_string(), 2, 1));
+    }
+}
@@ -1,12 +1,14 @@
-use crate::diff::{Hunk, Line, RichHunk, RichHunks};
+use crate::diff::{Line, Move, RichHunk, RichHunks};
+use crate::diff_result::Position;
 use crate::render::{
     default_option, opt_color_def, ColorDef, DisplayData, EmphasizedStyle, RegularStyle, Renderer,
 };
+use crate::symbols::Symbol;
 use anyhow::Result;
-use console::{Color, Style, Term};
+use console::{measure_text_width, Color, Style, Term};
 use log::{debug, error, info};
 use serde::{Deserialize, Serialize};
-use std::{cmp::max, io::Write};
+use std::{cmp::max, collections::HashMap, fmt, io::Write, mem::discriminant, ops::Range};
 
 /// The ascii separator used after the diff title
 const TITLE_SEPARATOR: &str = "=";
@@ -26,6 +28,25 @@ const HUNK_TITLE_SEPARATOR: &str = "-";
 pub struct Unified {
     pub addition: TextStyle,
     pub deletion: TextStyle,
+    /// The number of unchanged lines to display before and after each hunk.
+    ///
+    /// This is similar to `diff -U N`. If the context of two neighboring hunks from the same
+    /// document overlaps, the hunks are merged and displayed as a single block.
+    pub context_lines: usize,
+    /// Whether to display the line numbers from the old and new documents in a gutter before
+    /// each line.
+    ///
+    /// The line numbers are the same as the ones in the hunk titles, counting from 1. Lines from
+    /// the old document have their number in the first column and lines from the new document
+    /// have their number in the second column.
+    pub line_numbers: bool,
+    /// Whether to display the deletions and additions that make up a change next to each other.
+    ///
+    /// By default, hunks are displayed in the order the diff engine produced them, which can put
+    /// a deletion far away from the addition that replaced it. With this option, hunks that
+    /// overlap in the edit script are displayed together, with the old hunks before the new ones,
+    /// so each change reads as "before then after".
+    pub interleave: bool,
 }
 
 /// Text style options for additions or deleetions.
@@ -70,6 +91,9 @@ impl Default for Unified {
                 underline: false,
                 prefix: "- ".into(),
             },
+            context_lines: 0,
+            line_numbers: false,
+            interleave: false,
         }
     }
 }
@@ -121,7 +145,9 @@ impl Renderer for Unified {
         data: &DisplayData,
         term_info: Option<&Term>,
     ) -> Result<()> {
-        let DisplayData { hunks, old, new } = &data;
+        let DisplayData {
+            hunks, old, new, ..
+        } = &data;
         let old_fmt = FormattingDirectives::from(&self.deletion);
         let new_fmt = FormattingDirectives::from(&self.addition);
 
@@ -140,13 +166,33 @@ impl Renderer for Unified {
             term_info,
         )?;
 
-        for hunk_wrapper in &hunks.0 {
-            match hunk_wrapper {
-                RichHunk::Old(hunk) => {
-                    self.print_hunk(writer, &old_lines, hunk, &old_fmt)?;
+        // Hunks whose context overlaps are printed together, so we work with blocks of hunks
+        // rather than individual hunks.
+        let order = self.hunk_order(data);
+        let blocks = context_blocks(
+            &hunks.0,
+            &order,
+            self.context_lines,
+            old_lines.len(),
+            new_lines.len(),
+        );
+
+        // Every line number in the gutter is padded to the width of the largest one, so the text
+        // of each line lines up
+        let gutter_width = self.line_numbers.then(|| {
+            let largest = blocks.iter().map(|block| block.last_line + 1).max();
+            largest.unwrap_or_default().to_string().len()
+        });
+
+        for block in &blocks {
+            match &hunks.0[block.hunks[0]] {
+                RichHunk::Old(_) => {
+                    let gutter = gutter_width.map(|width| Gutter::Old { width });
+                    self.print_block(writer, &old_lines, data, block, &old_fmt, gutter)?;
                 }
-                RichHunk::New(hunk) => {
-                    self.print_hunk(writer, &new_lines, hunk, &new_fmt)?;
+                RichHunk::New(_) => {
+                    let gutter = gutter_width.map(|width| Gutter::New { width });
+                    self.print_block(writer, &new_lines, data, block, &new_fmt, gutter)?;
                 }
             }
         }
@@ -155,6 +201,15 @@ impl Renderer for Unified {
 }
 
 impl Unified {
+    /// Get the indices of the hunks in the order they should be displayed.
+    fn hunk_order(&self, data: &DisplayData) -> Vec<usize> {
+        if self.interleave {
+            data.hunk_groups().concat()
+        } else {
+            (0..data.hunks.0.len()).collect()
+        }
+    }
+
     /// Print the title for the diff
     ///
     /// This will print the two files being compared. This will also attempt to modify the layout
@@ -232,23 +287,55 @@ impl Unified {
         Ok(())
     }
 
-    /// Print a [hunk](Hunk) to `stdout`
-    fn print_hunk(
+    /// Print a [block](ContextBlock) of hunks to `stdout`
+    ///
+    /// Every line in the block that isn't part of a hunk is printed as a context line. The title
+    /// names the symbol that encloses the first hunk in the block, and if any of the hunks in the
+    /// block were moved, the title notes where they were moved to or from.
+    ///
+    /// If `gutter` is set, the line number of each line is printed before its prefix.
+    fn print_block(
         &self,
         term: &mut dyn Write,
         lines: &[&str],
-        hunk: &Hunk,
+        data: &DisplayData,
+        block: &ContextBlock,
         fmt: &FormattingDirectives,
+        gutter: Option<Gutter>,
     ) -> Result<()> {
         debug!(
-            "Printing hunk (lines {} - {})",
-            hunk.first_line().unwrap(),
-            hunk.last_line().unwrap()
+            "Printing block (lines {} - {})",
+            block.first_line, block.last_line
         );
-        self.print_hunk_title(term, hunk, fmt)?;
-
-        for line in &hunk.0 {
-            let line_index = line.line_index;
+        let hunks = &data.hunks.0;
+        let move_notes: Vec<_> = block
+            .hunks
+            .iter()
+            .flat_map(|&hunk_idx| move_notes(&data.moves, hunk_idx, 1))
+            .collect();
+        let symbol = data
+            .symbols
+            .get(block.hunks[0])
+            .and_then(|symbols| symbols.last());
+        // Lines are numbered from 1 like in editors, which matches the gutter
+        let title = HunkTitle {
+            first_line: block.first_line + 1,
+            last_line: block.last_line + 1,
+            symbol,
+            notes: &move_notes,
+        };
+        self.print_hunk_title(term, &title, fmt)?;
+
+        // The edited lines in the block, keyed by their line index, so we can tell whether a line
+        // should be printed as an edit or as context.
+        let edited_lines: HashMap<usize, &Line> = block
+            .hunks
+            .iter()
+            .flat_map(|&hunk_idx| hunks[hunk_idx].as_ref().0.iter())
+            .map(|line| (line.line_index, line))
+            .collect();
+
+        for line_index in block.first_line..=block.last_line {
             // It's find for this to be fatal in debug builds. We want to avoid crashing in
             // release.
             debug_assert!(line_index < lines.len());
@@ -258,13 +345,23 @@ impl Unified {
             }
             let text = lines[line_index];
             debug!("Printing line {line_index}");
-            self.print_line(term, text, line, fmt)?;
+            let line_number = gutter.map(|gutter| gutter.line_number(line_index));
+            if let Some(line) = edited_lines.get(&line_index) {
+                if let Some(line_number) = line_number {
+                    write!(term, "{}", fmt.regular.0.apply_to(line_number))?;
+                }
+                self.print_line(term, text, line, fmt)?;
+            } else {
+                if let Some(line_number) = line_number {
+                    write!(term, "{line_number}")?;
+                }
+                self.print_context_line(term, text, fmt)?;
+            }
             debug!("End line {line_index}");
         }
         debug!(
-            "End hunk (lines {} - {})",
-            hunk.first_line().unwrap(),
-            hunk.last_line().unwrap()
+            "End block (lines {} - {})",
+            block.first_line, block.last_line
         );
         Ok(())
     }
@@ -272,22 +369,15 @@ impl Unified {
     /// Print the title of a hunk to stdout
     ///
     /// This will print the line numbers that correspond to the hunk using the color directive for
-    /// that file, so the user has some context for the text that's being displayed.
+    /// that file, so the user has some context for the text that's being displayed. The name of the
+    /// enclosing symbol follows the line numbers, and any notes are appended in parentheses.
     fn print_hunk_title(
         &self,
         term: &mut dyn Write,
-        hunk: &Hunk,
+        title: &HunkTitle,
         fmt: &FormattingDirectives,
     ) -> Result<()> {
-        let first_line = hunk.first_line().unwrap();
-        let last_line = hunk.last_line().unwrap();
-
-        // We don't need to display a range `x - x:` since `x:` is terser and clearer
-        let title_str = if last_line - first_line == 0 {
-            format!("\n{first_line}:")
-        } else {
-            format!("\n{first_line} - {last_line}:")
-        };
+        let title_str = format!("\n{title}:");
 
         debug!("Title string has length of {}", title_str.len());
 
@@ -299,6 +389,21 @@ impl Unified {
         Ok(())
     }
 
+    /// Print an unchanged line that provides context for a hunk
+    ///
+    /// Context lines are printed without any styling. The prefix is replaced with whitespace so
+    /// the text lines up with the edited lines.
+    fn print_context_line(
+        &self,
+        term: &mut dyn Write,
+        text: &str,
+        fmt: &FormattingDirectives,
+    ) -> Result<()> {
+        let padding = " ".repeat(measure_text_width(fmt.prefix.as_ref()));
+        writeln!(term, "{padding}{text}")?;
+        Ok(())
+    }
+
     /// Print a line with edits
     ///
     /// This is a generic helper method for additions and deletions, since the logic is very
@@ -348,6 +453,186 @@ impl Unified {
     }
 }
 
+/// A group of consecutive hunks from the same document that are displayed together.
+///
+/// Hunks are grouped when their context lines overlap or touch, so the user sees a single block of
+/// text instead of the same context lines printed twice.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ContextBlock {
+    /// The indices of the hunks in the block, which is never empty
+    hunks: Vec<usize>,
+    /// The first line to display, including context lines
+    first_line: usize,
+    /// The last line to display (inclusive), including context lines
+    last_line: usize,
+}
+
+/// The parts of the title of a hunk
+struct HunkTitle<'a> {
+    /// The number of the first line of the block, counting from 1
+    first_line: usize,
+    /// The number of the last line of the block (inclusive), counting from 1
+    last_line: usize,
+    /// The innermost symbol that encloses the hunk
+    symbol: Option<&'a Symbol>,
+    /// Notes about the hunk, like where it was moved to
+    notes: &'a [String],
+}
+
+impl fmt::Display for HunkTitle<'_> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", line_range(self.first_line, self.last_line))?;
+        if let Some(symbol) = self.symbol {
+            write!(f, " in {}", symbol.name)?;
+        }
+        if !self.notes.is_empty() {
+            write!(f, " ({})", self.notes.join(", "))?;
+        }
+        Ok(())
+    }
+}
+
+/// The gutter that displays line numbers before each line of a block
+///
+/// The gutter has a column for each document, and each line has its number in the column for the
+/// document it's from. `width` is the width of each column.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Gutter {
+    Old { width: usize },
+    New { width: usize },
+}
+
+impl Gutter {
+    /// Format the gutter for a line, like `4   ` for the fourth line of the old document.
+    ///
+    /// `line_index` is 0-based, but lines are numbered from 1 like in editors.
+    fn line_number(self, line_index: usize) -> String {
+        let number = (line_index + 1).to_string();
+        let (old, new, width) = match self {
+            Gutter::Old { width } => (number, String::new(), width),
+            Gutter::New { width } => (String::new(), number, width),
+        };
+        format!("{old:>width$} {new:>width$} ")
+    }
+}
+
+/// Format an inclusive range of line numbers.
+///
+/// We don't need to display a range `x - x` since `x` is terser and clearer.
+pub fn line_range(first_line: usize, last_line: usize) -> String {
+    if first_line == last_line {
+        format!("{first_line}")
+    } else {
+        format!("{first_line} - {last_line}")
+    }
+}
+
+/// Describe where the parts of a hunk that were moved were moved to or from.
+///
+/// `first_line_number` is the number of the first line of a document, which is 1 to match the hunk
+/// titles and editors.
+pub fn move_notes(
+    moves: &[Move],
+    hunk_idx: usize,
+    first_line_number: usize,
+) -> impl Iterator<Item = String> + '_ {
+    let range = move |range: &Range<Position>| {
+        line_range(
+            range.start.row + first_line_number,
+            range.end.row + first_line_number,
+        )
+    };
+    moves.iter().filter_map(move |m| {
+        if m.old_hunk == hunk_idx {
+            Some(format!("moved to {}", range(&m.destination)))
+        } else if m.new_hunk == hunk_idx {
+            Some(format!("moved from {}", range(&m.source)))
+        } else {
+            None
+        }
+    })
+}
+
+/// Group hunks into blocks that should be displayed together, given the number of context lines
+/// to display around each hunk.
+///
+/// `order` has the indices of the hunks in the order they're displayed. Only hunks that are
+/// displayed next to each other and come from the same document are merged. `old_len` and
+/// `new_len` are the number of lines in the old and new documents, which are used to clamp the
+/// context.
+///
+/// Context lines that were already displayed for a previous block of the same document are not
+/// repeated.
+fn context_blocks(
+    hunks: &[RichHunk],
+    order: &[usize],
+    context_lines: usize,
+    old_len: usize,
+    new_len: usize,
+) -> Vec<ContextBlock> {
+    // The first line of the next hunk from the same document, for each hunk. Context lines can't
+    // run into the next hunk, otherwise an edited line would be displayed as a context line.
+    let mut next_first_lines = vec![None; hunks.len()];
+    let mut next_old: Option<usize> = None;
+    let mut next_new: Option<usize> = None;
+
+    for &i in order.iter().rev() {
+        let hunk_wrapper = &hunks[i];
+        let next_first_line = match hunk_wrapper {
+            RichHunk::Old(_) => &mut next_old,
+            RichHunk::New(_) => &mut next_new,
+        };
+        next_first_lines[i] = *next_first_line;
+        *next_first_line = hunk_wrapper.as_ref().first_line();
+    }
+
+    let mut blocks: Vec<ContextBlock> = Vec::new();
+    // The last line that was displayed for each document
+    let mut last_old: Option<usize> = None;
+    let mut last_new: Option<usize> = None;
+
+    for &i in order {
+        let hunk_wrapper = &hunks[i];
+        let (num_lines, last_displayed) = match hunk_wrapper {
+            RichHunk::Old(_) => (old_len, &mut last_old),
+            RichHunk::New(_) => (new_len, &mut last_new),
+        };
+        let hunk = hunk_wrapper.as_ref();
+        let first_line = hunk.first_line().unwrap();
+        let last_line = hunk.last_line().unwrap();
+        let context_start = first_line.saturating_sub(context_lines);
+        let mut context_end = (last_line + context_lines).min(num_lines.saturating_sub(1));
+        if let Some(next_first_line) = next_first_lines[i] {
+            context_end = context_end.min(next_first_line.saturating_sub(1));
+        }
+        let context_end = max(last_line, context_end);
+
+        // The last block always ends with the hunk that was displayed before this one
+        let can_merge = blocks.last().is_some_and(|block: &ContextBlock| {
+            discriminant(&hunks[block.hunks[0]]) == discriminant(hunk_wrapper)
+                && context_start <= block.last_line + 1
+        });
+
+        if can_merge {
+            let block = blocks.last_mut().unwrap();
+            block.hunks.push(i);
+            block.last_line = context_end;
+        } else {
+            let first_line = match last_displayed {
+                Some(last) => max(context_start, *last + 1),
+                None => context_start,
+            };
+            blocks.push(ContextBlock {
+                hunks: vec![i],
+                first_line,
+                last_line: context_end,
+            });
+        }
+        *last_displayed = Some(context_end);
+    }
+    blocks
+}
+
 impl From<&TextStyle> for RegularStyle {
     fn from(fmt: &TextStyle) -> Self {
         let mut style = Style::default();
@@ -375,3 +660,210 @@ impl From<&TextStyle> for EmphasizedStyle {
         EmphasizedStyle(style)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::diff::Hunk;
+    use pretty_assertions::assert_eq as p_assert_eq;
+
+    /// Create a hunk spanning the given (inclusive) line range without any entries.
+    fn hunk(first_line: usize, last_line: usize) -> Hunk<'static> {
+        Hunk((first_line..=last_line).map(Line::new).collect())
+    }
+
+    /// The display order that keeps the hunks in their original order
+    fn in_order(hunks: &[RichHunk]) -> Vec<usize> {
+        (0..hunks.len()).collect()
+    }
+
+    #[test]
+    fn move_notes_for_hunks() {
+        let position = |row| Position { row, column: 0 };
+        let moves = vec![
+            Move {
+                old_hunk: 0,
+                new_hunk: 2,
+                source: position(1)..position(3),
+                destination: position(7)..position(7),
+            },
+            Move {
+                old_hunk: 0,
+                new_hunk: 3,
+                source: position(4)..position(4),
+                destination: position(9)..position(10),
+            },
+        ];
+        let notes = |hunk_idx| move_notes(&moves, hunk_idx, 0).collect::<Vec<_>>();
+        p_assert_eq!(notes(0), vec!["moved to 7", "moved to 9 - 10"]);
+        p_assert_eq!(notes(1), Vec::<String>::new());
+        p_assert_eq!(notes(2), vec!["moved from 1 - 3"]);
+        p_assert_eq!(
+            move_notes(&moves, 2, 1).collect::<Vec<_>>(),
+            vec!["moved from 2 - 4"]
+        );
+    }
+
+    #[test]
+    fn hunk_title() {
+        let symbol = Symbol {
+            kind: "function_item".into(),
+            name: "foo".into(),
+        };
+        let notes = vec!["moved to 7".to_string()];
+        let title = |first_line, last_line, symbol, notes| {
+            HunkTitle {
+                first_line,
+                last_line,
+                symbol,
+                notes,
+            }
+            .to_string()
+        };
+        p_assert_eq!(title(1, 1, None, &[]), "1");
+        p_assert_eq!(title(1, 3, Some(&symbol), &[]), "1 - 3 in foo");
+        p_assert_eq!(
+            title(1, 3, Some(&symbol), &notes),
+            "1 - 3 in foo (moved to 7)"
+        );
+        p_assert_eq!(title(2, 2, None, &notes), "2 (moved to 7)");
+    }
+
+    #[test]
+    fn gutter_line_numbers() {
+        p_assert_eq!(Gutter::Old { width: 2 }.line_number(3), " 4    ");
+        p_assert_eq!(Gutter::New { width: 2 }.line_number(3), "    4 ");
+        p_assert_eq!(Gutter::New { width: 2 }.line_number(9), "   10 ");
+        p_assert_eq!(Gutter::Old { width: 1 }.line_number(0), "1   ");
+    }
+
+    /// The hunk titles and the gutter number the lines the same way.
+    #[test]
+    fn titles_match_gutter() {
+        console::set_colors_enabled(false);
+        let data = DisplayData {
+            hunks: RichHunks(vec![RichHunk::New(Hunk(vec![Line::new(1)]))]),
+            old: crate::render::DocumentDiffData {
+                filename: "a.rs",
+                text: "fn main() {\n}\n",
+                language: "rust",
+            },
+            new: crate::render::DocumentDiffData {
+                filename: "b.rs",
+                text: "fn main() {\n    a();\n}\n",
+                language: "rust",
+            },
+            moves: Vec::new(),
+            symbols: Vec::new(),
+            groups: vec![vec![0]],
+        };
+        let renderer = Unified {
+            context_lines: 0,
+            line_numbers: true,
+            ..Unified::default()
+        };
+        let mut output = Vec::new();
+        renderer.render(&mut output, &data, None).unwrap();
+        let output = String::from_utf8(output).unwrap();
+        p_assert_eq!(
+            output.lines().skip(3).collect::<Vec<_>>(),
+            vec!["2:", "--", "  2 +     a();"]
+        );
+    }
+
+    #[test]
+    fn context_blocks_no_context() {
+        let hunks = vec![RichHunk::Old(hunk(1, 2)), RichHunk::Old(hunk(4, 4))];
+        let blocks = context_blocks(&hunks, &in_order(&hunks), 0, 10, 10);
+        let expected = vec![
+            ContextBlock {
+                hunks: vec![0],
+                first_line: 1,
+                last_line: 2,
+            },
+            ContextBlock {
+                hunks: vec![1],
+                first_line: 4,
+                last_line: 4,
+            },
+        ];
+        p_assert_eq!(expected, blocks);
+    }
+
+    #[test]
+    fn context_blocks_merge_overlapping() {
+        let hunks = vec![
+            RichHunk::Old(hunk(1, 2)),
+            RichHunk::Old(hunk(5, 5)),
+            RichHunk::Old(hunk(9, 9)),
+        ];
+        let blocks = context_blocks(&hunks, &in_order(&hunks), 1, 10, 10);
+        let expected = vec![
+            ContextBlock {
+                hunks: vec![0, 1],
+                first_line: 0,
+                last_line: 6,
+            },
+            ContextBlock {
+                hunks: vec![2],
+                first_line: 8,
+                last_line: 9,
+            },
+        ];
+        p_assert_eq!(expected, blocks);
+    }
+
+    #[test]
+    fn context_blocks_different_documents() {
+        let hunks = vec![
+            RichHunk::Old(hunk(3, 3)),
+            RichHunk::New(hunk(4, 4)),
+            RichHunk::Old(hunk(5, 5)),
+        ];
+        let blocks = context_blocks(&hunks, &in_order(&hunks), 2, 7, 5);
+        let expected = vec![
+            // The context can't run into the next hunk from the same document
+            ContextBlock {
+                hunks: vec![0],
+                first_line: 1,
+                last_line: 4,
+            },
+            ContextBlock {
+                hunks: vec![1],
+                first_line: 2,
+                last_line: 4,
+            },
+            // The context lines that were displayed by the first block aren't repeated
+            ContextBlock {
+                hunks: vec![2],
+                first_line: 5,
+                last_line: 6,
+            },
+        ];
+        p_assert_eq!(expected, blocks);
+    }
+
+    #[test]
+    fn context_blocks_interleaved() {
+        let hunks = vec![
+            RichHunk::Old(hunk(1, 2)),
+            RichHunk::New(hunk(1, 1)),
+            RichHunk::Old(hunk(3, 3)),
+        ];
+        // Both old hunks are displayed before the new hunk, so they're merged
+        let blocks = context_blocks(&hunks, &[0, 2, 1], 0, 10, 10);
+        let expected = vec![
+            ContextBlock {
+                hunks: vec![0, 2],
+                first_line: 1,
+                last_line: 3,
+            },
+            ContextBlock {
+                hunks: vec![1],
+                first_line: 1,
+                last_line: 1,
+            },
+        ];
+        p_assert_eq!(expected, blocks);
+    }
+}
@@ -0,0 +1,204 @@
+//! Utilities for finding the symbols that enclose a node, like the function or class it's in.
+//!
+//! This is similar to the `funcname` that git shows in hunk headers, but rather than matching the
+//! text of the lines before a hunk, we walk up the syntax tree.
+
+use crate::diff::{DocumentType, RichHunks};
+use crate::input_processing::scope_name;
+use phf::phf_map;
+use serde::{Deserialize, Serialize};
+use tree_sitter::Node as TSNode;
+
+/// A mapping of languages to the node kinds of their named definitions, like functions and classes
+///
+/// Kinds have to match exactly, so nodes that only refer to a definition, like a call or a type
+/// reference, aren't mistaken for one. Languages that aren't listed don't have any symbols.
+static DEFINITION_KINDS: phf::Map<&'static str, &'static [&'static str]> = phf_map! {
+    "bash" => &["function_definition"],
+    "c" => &["function_definition", "struct_specifier", "union_specifier", "enum_specifier"],
+    "c_sharp" => &[
+        "namespace_declaration",
+        "file_scoped_namespace_declaration",
+        "class_declaration",
+        "struct_declaration",
+        "interface_declaration",
+        "enum_declaration",
+        "record_declaration",
+        "method_declaration",
+        "constructor_declaration",
+        "local_function_statement",
+    ],
+    "cpp" => &[
+        "namespace_definition",
+        "function_definition",
+        "class_specifier",
+        "struct_specifier",
+        "union_specifier",
+        "enum_specifier",
+    ],
+    "go" => &["function_declaration", "method_declaration", "type_spec"],
+    "java" => &[
+        "class_declaration",
+        "interface_declaration",
+        "enum_declaration",
+        "record_declaration",
+        "annotation_type_declaration",
+        "method_declaration",
+        "constructor_declaration",
+    ],
+    "ocaml" => &["module_binding", "class_binding"],
+    "php" => &[
+        "namespace_definition",
+        "function_definition",
+        "class_declaration",
+        "interface_declaration",
+        "trait_declaration",
+        "enum_declaration",
+        "method_declaration",
+    ],
+    "python" => &["function_definition", "class_definition"],
+    "ruby" => &["method", "singleton_method", "class", "module"],
+    "rust" => &[
+        "mod_item",
+        "function_item",
+        "function_signature_item",
+        "struct_item",
+        "enum_item",
+        "union_item",
+        "trait_item",
+        "impl_item",
+        "macro_definition",
+    ],
+    "tsx" => &[
+        "internal_module",
+        "module",
+        "function_declaration",
+        "generator_function_declaration",
+        "class_declaration",
+        "abstract_class_declaration",
+        "interface_declaration",
+        "enum_declaration",
+        "method_definition",
+    ],
+    "typescript" => &[
+        "internal_module",
+        "module",
+        "function_declaration",
+        "generator_function_declaration",
+        "class_declaration",
+        "abstract_class_declaration",
+        "interface_declaration",
+        "enum_declaration",
+        "method_definition",
+    ],
+};
+
+/// Definition kinds that also refer to a type when they don't have a body, like the
+/// `struct_specifier` in C's `struct point p;`
+const BODY_REQUIRED_KINDS: &[&str] = &[
+    "class_specifier",
+    "struct_specifier",
+    "union_specifier",
+    "enum_specifier",
+];
+
+/// A named definition that encloses part of a document, like a function or a class
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub struct Symbol {
+    /// The tree-sitter node kind of the definition, like `function_item`
+    pub kind: String,
+    /// The name of the definition
+    pub name: String,
+}
+
+impl Symbol {
+    /// Create a symbol from a node, if the node is a named definition in `language`.
+    fn new(node: TSNode, text: &str, language: &str) -> Option<Self> {
+        let kind = node.kind();
+        let is_definition = DEFINITION_KINDS
+            .get(language)
+            .is_some_and(|kinds| kinds.contains(&kind))
+            && (!BODY_REQUIRED_KINDS.contains(&kind) || node.child_by_field_name("body").is_some());
+        if !is_definition {
+            return None;
+        }
+        let name = scope_name(node, text).or_else(|| {
+            // Implementations, like Rust's `impl Foo`, are named after the type they're for
+            node.child_by_field_name("type")
+                .filter(|_| kind == "impl_item")
+                .and_then(|ty| text.get(ty.byte_range()))
+        })?;
+        Some(Symbol {
+            kind: kind.to_string(),
+            name: name.to_string(),
+        })
+    }
+}
+
+/// Get the symbols that enclose a node, from the outermost to the innermost.
+///
+/// The node itself is included if it's a symbol.
+#[must_use]
+pub fn enclosing_symbols(node: TSNode, text: &str, language: &str) -> Vec<Symbol> {
+    let mut symbols: Vec<_> = std::iter::successors(Some(node), TSNode::parent)
+        .filter_map(|ancestor| Symbol::new(ancestor, text, language))
+        .collect();
+    symbols.reverse();
+    symbols
+}
+
+/// Get the symbols that enclose the first entry of each hunk.
+///
+/// `old` and `new` are the texts and languages of the documents the hunks come from.
+#[must_use]
+pub fn hunk_symbols(hunks: &RichHunks, old: (&str, &str), new: (&str, &str)) -> Vec<Vec<Symbol>> {
+    hunks
+        .0
+        .iter()
+        .map(|hunk| {
+            let (text, language) = match hunk {
+                DocumentType::Old(_) => old,
+                DocumentType::New(_) => new,
+            };
+            hunk.as_ref()
+                .entries()
+                .next()
+                .map(|entry| enclosing_symbols(entry.reference, text, language))
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+// NOTE: these tests have to be gated behind the 'static-grammar-libs' cargo feature, otherwise the
+// grammars aren't available to parse the documents.
+#[cfg(all(test, feature = "static-grammar-libs"))]
+mod tests {
+    use super::*;
+    use crate::parse::{generate_language, GrammarConfig};
+    use test_case::test_case;
+    use tree_sitter::{Parser, Point};
+
+    #[test_case("rust", "mod a {\n    impl Foo {\n        fn bar() {\n            x();\n        }\n    }\n}\n", Point::new(3, 12), &["a", "Foo", "bar"] ; "rust")]
+    #[test_case("rust", "fn main() {\n    let c = Config { a: 1 };\n}\n", Point::new(1, 21), &["main"] ; "rust struct expression")]
+    #[test_case("python", "class A:\n    def f(self):\n        return 1\n", Point::new(2, 15), &["A", "f"] ; "python")]
+    #[test_case("rust", "enum E {\n    A { x: u8 },\n}\n", Point::new(1, 8), &["E"] ; "rust enum variant")]
+    #[test_case("python", "x = 1\n", Point::new(0, 0), &[] ; "no symbols")]
+    #[test_case("java", "class A {\n    void f() {\n        g();\n    }\n}\n", Point::new(2, 8), &["A", "f"] ; "java method call")]
+    #[test_case("c", "int f() {\n    struct point p;\n    return 0;\n}\n", Point::new(1, 11), &["f"] ; "c struct type")]
+    #[test_case("c", "struct point {\n    int x;\n};\n", Point::new(1, 8), &["point"] ; "c struct definition")]
+    fn test_enclosing_symbols(language: &str, text: &str, point: Point, expected: &[&str]) {
+        let ts_language = generate_language(language, &GrammarConfig::default()).unwrap();
+        let mut parser = Parser::new();
+        parser.set_language(&ts_language).unwrap();
+        let tree = parser.parse(text, None).unwrap();
+        let node = tree
+            .root_node()
+            .descendant_for_point_range(point, point)
+            .unwrap();
+        let names: Vec<_> = enclosing_symbols(node, text, language)
+            .into_iter()
+            .map(|symbol| symbol.name)
+            .collect();
+        assert_eq!(names, expected);
+    }
+}
@@ -0,0 +1,590 @@
+//! A diff engine that matches the syntax trees of two documents.
+//!
+//! [`Myers`] diffs the flattened leaves of the syntax trees, so it doesn't know anything about the
+//! structure of the documents. [`TreeDiff`] matches the nodes of the trees instead, using the
+//! approach from GumTree ("Fine-grained and Accurate Source Code Differencing", Falleri et al.):
+//!
+//! 1. A top-down pass matches the largest identical subtrees.
+//! 2. A bottom-up pass matches nodes whose descendants were mostly matched, and then matches the
+//!    remaining children of those nodes that have the same kind.
+//!
+//! This means that wrapping an expression in a function call is reported as an addition of the
+//! call around the expression, and changing the nesting of a block only reports the nodes that
+//! were added or removed around it.
+//!
+//! The edit script only consists of additions and deletions, so the matched leaves are reduced to
+//! the longest set of matches that preserves the order of both documents. Leaves that were moved
+//! are reported as deleted from their old location and added to their new location.
+
+use crate::diff::{longest_increasing_pairs, Engine, Myers};
+use crate::input_processing::{EditType, Entry};
+use std::collections::{hash_map::DefaultHasher, HashMap, HashSet, VecDeque};
+use std::hash::{Hash, Hasher};
+use std::ops::Range;
+use tree_sitter::Node as TSNode;
+
+/// A diff engine that matches subtrees of the syntax trees of the inputs
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TreeDiff {
+    /// The minimum height of a subtree for it to be matched in the top-down pass
+    ///
+    /// Small subtrees like a lone identifier are frequently identical by coincidence, so they are
+    /// only matched if their ancestors are similar.
+    pub min_height: usize,
+
+    /// The minimum ratio of matched descendants for two nodes to be matched in the bottom-up pass
+    pub min_similarity: f64,
+}
+
+impl Default for TreeDiff {
+    fn default() -> Self {
+        Self {
+            min_height: 2,
+            min_similarity: 0.5,
+        }
+    }
+}
+
+/// A node in a [`Tree`]
+#[derive(Debug)]
+struct Node {
+    /// The kind of the syntax node
+    kind_id: u16,
+    /// The index of the parent node, if this isn't the root
+    parent: Option<usize>,
+    /// The indices of the child nodes
+    children: Vec<usize>,
+    /// The number of nodes in the subtree rooted at this node, including itself
+    size: usize,
+    /// The height of the subtree rooted at this node, where a leaf has a height of 1
+    height: usize,
+    /// A hash of the kinds and text in the subtree, which is equal for identical subtrees
+    hash: u64,
+    /// The range of entries that correspond to the leaves of the subtree
+    entries: Range<usize>,
+}
+
+impl Node {
+    fn is_leaf(&self) -> bool {
+        self.children.is_empty()
+    }
+}
+
+/// A syntax tree, pruned to the nodes that have entries
+///
+/// The nodes are stored in post-order, so the descendants of a node directly precede it and the
+/// leaves are in the same order as the entries.
+#[derive(Debug)]
+struct Tree {
+    nodes: Vec<Node>,
+}
+
+impl Tree {
+    /// Reconstruct the tree that a list of entries was created from.
+    fn new(entries: &[Entry]) -> Self {
+        let mut nodes = Vec::new();
+        let Some(first) = entries.first() else {
+            return Tree { nodes };
+        };
+        let mut root = first.reference;
+        while let Some(parent) = root.parent() {
+            root = parent;
+        }
+
+        // Splitting a leaf on graphemes creates several consecutive entries for the same node
+        let mut leaf_entries: HashMap<usize, Range<usize>> = HashMap::new();
+        for (idx, entry) in entries.iter().enumerate() {
+            leaf_entries
+                .entry(entry.reference.id())
+                .and_modify(|range| range.end = idx + 1)
+                .or_insert(idx..idx + 1);
+        }
+        Tree::build(&mut nodes, root, entries, &leaf_entries);
+        Tree { nodes }
+    }
+
+    /// Add the subtree rooted at `node` to `nodes`, skipping any nodes that don't have entries.
+    ///
+    /// This returns the index of the node, if it was added.
+    fn build(
+        nodes: &mut Vec<Node>,
+        node: TSNode,
+        entries: &[Entry],
+        leaf_entries: &HashMap<usize, Range<usize>>,
+    ) -> Option<usize> {
+        let mut hasher = DefaultHasher::new();
+        node.kind_id().hash(&mut hasher);
+
+        if node.child_count() == 0 {
+            let range = leaf_entries.get(&node.id())?.clone();
+            entries[range.clone()].hash(&mut hasher);
+            nodes.push(Node {
+                kind_id: node.kind_id(),
+                parent: None,
+                children: Vec::new(),
+                size: 1,
+                height: 1,
+                hash: hasher.finish(),
+                entries: range,
+            });
+            return Some(nodes.len() - 1);
+        }
+
+        let mut cursor = node.walk();
+        let children: Vec<usize> = node
+            .children(&mut cursor)
+            .filter_map(|child| Tree::build(nodes, child, entries, leaf_entries))
+            .collect();
+        let (first, last) = (children.first()?, children.last()?);
+
+        let idx = nodes.len();
+        let mut size = 1;
+        let mut height = 0;
+        for &child in &children {
+            nodes[child].hash.hash(&mut hasher);
+            nodes[child].parent = Some(idx);
+            size += nodes[child].size;
+            height = height.max(nodes[child].height);
+        }
+        let entries = nodes[*first].entries.start..nodes[*last].entries.end;
+        nodes.push(Node {
+            kind_id: node.kind_id(),
+            parent: None,
+            children,
+            size,
+            height: height + 1,
+            hash: hasher.finish(),
+            entries,
+        });
+        Some(idx)
+    }
+
+    /// The indices of the descendants of a node, not including the node itself
+    fn descendants(&self, idx: usize) -> Range<usize> {
+        idx + 1 - self.nodes[idx].size..idx
+    }
+
+    /// The index of the root node, if the tree isn't empty
+    fn root(&self) -> Option<usize> {
+        self.nodes.len().checked_sub(1)
+    }
+}
+
+/// The matches between the nodes of the old and new trees
+struct Mappings {
+    old_to_new: Vec<Option<usize>>,
+    new_to_old: Vec<Option<usize>>,
+}
+
+impl Mappings {
+    fn new(old: &Tree, new: &Tree) -> Self {
+        Mappings {
+            old_to_new: vec![None; old.nodes.len()],
+            new_to_old: vec![None; new.nodes.len()],
+        }
+    }
+
+    fn add(&mut self, old: usize, new: usize) {
+        self.old_to_new[old] = Some(new);
+        self.new_to_old[new] = Some(old);
+    }
+}
+
+impl TreeDiff {
+    /// Match the largest identical subtrees of the two trees.
+    fn match_top_down(
+        &self,
+        (old, old_entries): (&Tree, &[Entry]),
+        (new, new_entries): (&Tree, &[Entry]),
+        mappings: &mut Mappings,
+    ) {
+        let mut new_by_hash: HashMap<u64, Vec<usize>> = HashMap::new();
+        for (idx, node) in new.nodes.iter().enumerate() {
+            if node.height >= self.min_height {
+                new_by_hash.entry(node.hash).or_default().push(idx);
+            }
+        }
+
+        // Visit the tallest subtrees first so subtrees are matched as a whole rather than in
+        // pieces. The sort is stable, so subtrees with the same height stay in document order.
+        let mut old_order: Vec<usize> = (0..old.nodes.len())
+            .filter(|&idx| old.nodes[idx].height >= self.min_height)
+            .collect();
+        old_order.sort_by_key(|&idx| std::cmp::Reverse(old.nodes[idx].height));
+
+        for old_idx in old_order {
+            if mappings.old_to_new[old_idx].is_some() {
+                continue;
+            }
+            let Some(candidates) = new_by_hash.get(&old.nodes[old_idx].hash) else {
+                continue;
+            };
+            // If there are several identical subtrees, we pick the one with the closest relative
+            // position in its document
+            let position =
+                |entries: &Range<usize>, total: usize| entries.start as f64 / total as f64;
+            let old_position = position(&old.nodes[old_idx].entries, old_entries.len());
+            let best = candidates
+                .iter()
+                .copied()
+                .filter(|&new_idx| {
+                    mappings.new_to_old[new_idx].is_none()
+                        && isomorphic((old, old_entries, old_idx), (new, new_entries, new_idx))
+                })
+                .min_by(|&a, &b| {
+                    let distance = |idx: usize| {
+                        (position(&new.nodes[idx].entries, new_entries.len()) - old_position).abs()
+                    };
+                    distance(a).total_cmp(&distance(b))
+                });
+
+            if let Some(new_idx) = best {
+                // Isomorphic subtrees have the same shape, so their nodes line up in post-order
+                let size = old.nodes[old_idx].size;
+                for offset in 0..size {
+                    mappings.add(old_idx + 1 - size + offset, new_idx + 1 - size + offset);
+                }
+            }
+        }
+    }
+
+    /// Match the nodes whose descendants are similar, then match their remaining children.
+    fn match_bottom_up(&self, old: &Tree, new: &Tree, mappings: &mut Mappings) {
+        for old_idx in 0..old.nodes.len() {
+            if old.nodes[old_idx].is_leaf() || mappings.old_to_new[old_idx].is_some() {
+                continue;
+            }
+
+            // The candidates are the unmatched ancestors of the nodes that this node's
+            // descendants were matched to, with the same kind as this node
+            let mut candidates = Vec::new();
+            let mut visited = HashSet::new();
+            for descendant in old.descendants(old_idx) {
+                let mut ancestor =
+                    mappings.old_to_new[descendant].and_then(|n| new.nodes[n].parent);
+                while let Some(new_idx) = ancestor {
+                    if !visited.insert(new_idx) {
+                        break;
+                    }
+                    if mappings.new_to_old[new_idx].is_none()
+                        && new.nodes[new_idx].kind_id == old.nodes[old_idx].kind_id
+                    {
+                        candidates.push(new_idx);
+                    }
+                    ancestor = new.nodes[new_idx].parent;
+                }
+            }
+
+            let best = candidates
+                .into_iter()
+                .map(|new_idx| (new_idx, similarity(old, old_idx, new, new_idx, mappings)))
+                .max_by(|(_, a), (_, b)| a.total_cmp(b));
+            if let Some((new_idx, similarity)) = best {
+                if similarity >= self.min_similarity {
+                    mappings.add(old_idx, new_idx);
+                    recover(old, old_idx, new, new_idx, mappings);
+                }
+            }
+        }
+
+        // The roots always correspond to each other
+        if let (Some(old_root), Some(new_root)) = (old.root(), new.root()) {
+            if mappings.old_to_new[old_root].is_none()
+                && mappings.new_to_old[new_root].is_none()
+                && old.nodes[old_root].kind_id == new.nodes[new_root].kind_id
+            {
+                mappings.add(old_root, new_root);
+                recover(old, old_root, new, new_root, mappings);
+            }
+        }
+    }
+}
+
+/// Check whether two subtrees have the same shape, kinds and text.
+///
+/// This guards against hash collisions when matching subtrees by their hash.
+fn isomorphic(
+    (old, old_entries, old_idx): (&Tree, &[Entry], usize),
+    (new, new_entries, new_idx): (&Tree, &[Entry], usize),
+) -> bool {
+    let (old_node, new_node) = (&old.nodes[old_idx], &new.nodes[new_idx]);
+    old_node.size == new_node.size
+        && old_entries[old_node.entries.clone()] == new_entries[new_node.entries.clone()]
+        && old
+            .descendants(old_idx)
+            .zip(new.descendants(new_idx))
+            .all(|(a, b)| {
+                old.nodes[a].kind_id == new.nodes[b].kind_id
+                    && old.nodes[a].children.len() == new.nodes[b].children.len()
+            })
+}
+
+/// The ratio of the descendants of two nodes that were matched with each other (the Dice
+/// coefficient).
+fn similarity(old: &Tree, old_idx: usize, new: &Tree, new_idx: usize, mappings: &Mappings) -> f64 {
+    let old_descendants = old.descendants(old_idx);
+    let new_descendants = new.descendants(new_idx);
+    let common = old_descendants
+        .clone()
+        .filter(|&idx| mappings.old_to_new[idx].is_some_and(|n| new_descendants.contains(&n)))
+        .count();
+    2.0 * common as f64 / (old_descendants.len() + new_descendants.len()) as f64
+}
+
+/// The largest number of cells in the table of [`longest_common_subsequence`] in [`recover`]
+///
+/// Nodes with a lot of unmatched children, like a long list of statements, would otherwise need a
+/// table that takes gigabytes.
+const MAX_LCS_CELLS: usize = 1 << 22;
+
+/// Match the unmatched children of two matched nodes that have the same kind, in order.
+///
+/// If there are too many children to find the longest common subsequence of their kinds, the
+/// children of each kind are paired up in order instead. This recurses into the children that
+/// were matched.
+fn recover(old: &Tree, old_idx: usize, new: &Tree, new_idx: usize, mappings: &mut Mappings) {
+    let unmatched = |tree: &Tree, idx: usize, mapped: &[Option<usize>]| -> Vec<usize> {
+        tree.nodes[idx]
+            .children
+            .iter()
+            .copied()
+            .filter(|&child| mapped[child].is_none())
+            .collect()
+    };
+    let old_children = unmatched(old, old_idx, &mappings.old_to_new);
+    let new_children = unmatched(new, new_idx, &mappings.new_to_old);
+    let pairs = if old_children.len().saturating_mul(new_children.len()) > MAX_LCS_CELLS {
+        match_by_key(
+            &old_children,
+            &new_children,
+            |&idx| old.nodes[idx].kind_id,
+            |&idx| new.nodes[idx].kind_id,
+        )
+    } else {
+        longest_common_subsequence(&old_children, &new_children, |&a, &b| {
+            old.nodes[a].kind_id == new.nodes[b].kind_id
+        })
+    };
+    for (a, b) in pairs {
+        mappings.add(a, b);
+        recover(old, a, new, b, mappings);
+    }
+}
+
+/// Find the longest common subsequence of two slices, where elements are compared with `eq`.
+///
+/// This returns the pairs of elements in the subsequence.
+fn longest_common_subsequence<T: Copy>(
+    a: &[T],
+    b: &[T],
+    eq: impl Fn(&T, &T) -> bool,
+) -> Vec<(T, T)> {
+    // lengths[i][j] is the length of the LCS of a[i..] and b[j..]
+    let mut lengths = vec![vec![0_usize; b.len() + 1]; a.len() + 1];
+    for i in (0..a.len()).rev() {
+        for j in (0..b.len()).rev() {
+            lengths[i][j] = if eq(&a[i], &b[j]) {
+                lengths[i + 1][j + 1] + 1
+            } else {
+                lengths[i + 1][j].max(lengths[i][j + 1])
+            };
+        }
+    }
+
+    let mut pairs = Vec::with_capacity(lengths[0][0]);
+    let (mut i, mut j) = (0, 0);
+    while i < a.len() && j < b.len() {
+        if eq(&a[i], &b[j]) {
+            pairs.push((a[i], b[j]));
+            i += 1;
+            j += 1;
+        } else if lengths[i + 1][j] >= lengths[i][j + 1] {
+            i += 1;
+        } else {
+            j += 1;
+        }
+    }
+    pairs
+}
+
+/// Pair up the elements of two slices that have the same key, in order.
+///
+/// The nth element of `a` with a key is paired with the nth element of `b` with that key, so
+/// unlike [`longest_common_subsequence`] the pairs don't have to preserve the order of both slices.
+fn match_by_key<T: Copy, K: Hash + Eq>(
+    a: &[T],
+    b: &[T],
+    a_key: impl Fn(&T) -> K,
+    b_key: impl Fn(&T) -> K,
+) -> Vec<(T, T)> {
+    let mut b_by_key: HashMap<K, VecDeque<T>> = HashMap::new();
+    for elem in b {
+        b_by_key.entry(b_key(elem)).or_default().push_back(*elem);
+    }
+    a.iter()
+        .filter_map(|elem| {
+            let other = b_by_key.get_mut(&a_key(elem))?.pop_front()?;
+            Some((*elem, other))
+        })
+        .collect()
+}
+
+/// Get the index of an element in a slice from a reference to the element.
+fn index_of<T>(slice: &[T], elem: &T) -> usize {
+    (elem as *const T as usize - slice.as_ptr() as usize) / std::mem::size_of::<T>()
+}
+
+impl<'elem, 'node> Engine<'elem, Entry<'node>> for TreeDiff
+where
+    'node: 'elem,
+{
+    type Container = Vec<EditType<&'elem Entry<'node>>>;
+
+    fn diff(&self, a: &'elem [Entry<'node>], b: &'elem [Entry<'node>]) -> Self::Container {
+        let old = Tree::new(a);
+        let new = Tree::new(b);
+        let mut mappings = Mappings::new(&old, &new);
+        self.match_top_down((&old, a), (&new, b), &mut mappings);
+        self.match_bottom_up(&old, &new, &mut mappings);
+
+        // The leaves are in document order, so we can find the matched leaves that don't cross
+        // each other
+        let leaf_pairs: Vec<(usize, usize)> = (0..old.nodes.len())
+            .filter(|&idx| old.nodes[idx].is_leaf())
+            .filter_map(|idx| {
+                mappings.old_to_new[idx]
+                    .filter(|&new_idx| new.nodes[new_idx].is_leaf())
+                    .map(|new_idx| (idx, new_idx))
+            })
+            .collect();
+
+        let mut old_kept = vec![false; a.len()];
+        let mut new_kept = vec![false; b.len()];
+        for (old_idx, new_idx) in longest_increasing_pairs(&leaf_pairs) {
+            let old_range = old.nodes[old_idx].entries.clone();
+            let new_range = new.nodes[new_idx].entries.clone();
+            old_kept[old_range.clone()].fill(true);
+            new_kept[new_range.clone()].fill(true);
+
+            // Matched leaves can have different text, like a renamed identifier, so we diff the
+            // entries within the leaves
+            let (old_leaf, new_leaf) = (&a[old_range.clone()], &b[new_range.clone()]);
+            if old_leaf == new_leaf {
+                continue;
+            }
+            for edit in Myers::default().diff(old_leaf, new_leaf) {
+                match edit {
+                    EditType::Deletion(entry) => {
+                        old_kept[old_range.start + index_of(old_leaf, entry)] = false;
+                    }
+                    EditType::Addition(entry) => {
+                        new_kept[new_range.start + index_of(new_leaf, entry)] = false;
+                    }
+                }
+            }
+        }
+
+        // The kept entries line up in order, so we can walk through both documents and emit the
+        // entries that weren't kept in between them
+        let mut edits = Vec::new();
+        let (mut i, mut j) = (0, 0);
+        while i < a.len() || j < b.len() {
+            if i < a.len() && !old_kept[i] {
+                edits.push(EditType::Deletion(&a[i]));
+                i += 1;
+            } else if j < b.len() && !new_kept[j] {
+                edits.push(EditType::Addition(&b[j]));
+                j += 1;
+            } else {
+                i += 1;
+                j += 1;
+            }
+        }
+        edits
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pretty_assertions::assert_eq as p_assert_eq;
+    use test_case::test_case;
+
+    #[test_case(b"ABCBDAB", b"BDCABA", 4 ; "interleaved")]
+    #[test_case(b"", b"ABC", 0 ; "empty input")]
+    #[test_case(b"ABC", b"ABC", 3 ; "identical inputs")]
+    fn lcs_length(a: &[u8], b: &[u8], expected: usize) {
+        let pairs = longest_common_subsequence(a, b, |x, y| x == y);
+        p_assert_eq!(pairs.len(), expected);
+        assert!(pairs.iter().all(|(x, y)| x == y));
+    }
+
+    #[test]
+    fn match_by_key_pairs_in_order() {
+        let (a, b) = (b"ABCA", b"CAAB");
+        let pairs = match_by_key(&[0, 1, 2, 3], &[0, 1, 2, 3], |&i| a[i], |&j| b[j]);
+        p_assert_eq!(pairs, vec![(0, 1), (1, 3), (2, 0), (3, 2)]);
+        assert!(match_by_key(b"AB", b"CD", |x| *x, |x| *x).is_empty());
+    }
+
+    #[test]
+    fn index_of_element() {
+        let values = [10, 20, 30];
+        p_assert_eq!(index_of(&values, &values[2]), 2);
+    }
+
+    /// Diff two snippets of Rust with the tree engine and return the text that was added and
+    /// deleted.
+    #[cfg(feature = "static-grammar-libs")]
+    fn tree_diff_rust(old: &str, new: &str) -> (String, String) {
+        use crate::{
+            input_processing::TreeSitterProcessor,
+            parse::{parse_text, GrammarConfig},
+        };
+
+        let old_tree = parse_text(old, "rust", &GrammarConfig::default()).unwrap();
+        let new_tree = parse_text(new, "rust", &GrammarConfig::default()).unwrap();
+        let processor = TreeSitterProcessor::default();
+        let old_entries = processor.process(&old_tree, old);
+        let new_entries = processor.process(&new_tree, new);
+        let (mut added, mut deleted) = (String::new(), String::new());
+        for edit in TreeDiff::default().diff(&old_entries, &new_entries) {
+            match edit {
+                EditType::Addition(entry) => added.push_str(&entry.text),
+                EditType::Deletion(entry) => deleted.push_str(&entry.text),
+            }
+        }
+        (added, deleted)
+    }
+
+    // NOTE: these have to be gated behind the 'static-grammar-libs' cargo feature, otherwise the
+    // grammar isn't available to parse the documents.
+    #[cfg(feature = "static-grammar-libs")]
+    #[test_case(
+        "fn main() { let x = a + b; }",
+        "fn main() { let x = f(a + b); }",
+        "f()",
+        "" ;
+        "wrap in call"
+    )]
+    #[test_case(
+        "fn main() { if c { run(a, b); } }",
+        "fn main() { run(a, b); }",
+        "",
+        "{ifc}" ;
+        "remove nesting"
+    )]
+    #[test_case(
+        "fn main() { let total = 1; }",
+        "fn main() { let totals = 1; }",
+        "s",
+        "" ;
+        "rename"
+    )]
+    fn tree_diff_structural_edits(old: &str, new: &str, added: &str, deleted: &str) {
+        p_assert_eq!(
+            tree_diff_rust(old, new),
+            (added.to_string(), deleted.to_string())
+        );
+    }
+}
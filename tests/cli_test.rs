@@ -0,0 +1,166 @@
+#[cfg(test)]
+mod tests {
+    use pretty_assertions::assert_eq;
+    use std::{
+        env, fs,
+        path::{Path, PathBuf},
+        process::{Command, Output},
+    };
+
+    fn test_data_dir(name: &str) -> PathBuf {
+        [env!("CARGO_MANIFEST_DIR"), "test_data", "dir_diff", name]
+            .iter()
+            .collect()
+    }
+
+    /// Run git in `dir`, panicking if it fails.
+    fn git(dir: &Path, args: &[&str]) -> Output {
+        let output = Command::new("git")
+            .current_dir(dir)
+            .args(["-c", "user.name=test", "-c", "user.email=test@example.com"])
+            .args(["-c", "commit.gpgsign=false"])
+            .args(args)
+            .output()
+            .unwrap();
+        assert!(output.status.success(), "git {args:?} failed: {output:?}");
+        output
+    }
+
+    /// Create a git repository in a temporary directory with two commits, where `src/main.rs`
+    /// changes from `old` to `new`.
+    fn git_repo(name: &str, old: &str, new: &str) -> PathBuf {
+        let dir = env::temp_dir().join(format!("diffsitter-{name}-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        git(&dir, &["init", "-q"]);
+        for (text, message) in [(old, "old"), (new, "new")] {
+            fs::write(dir.join("src/main.rs"), text).unwrap();
+            git(&dir, &["add", "-A"]);
+            git(&dir, &["commit", "-q", "-m", message]);
+        }
+        dir
+    }
+
+    /// Diff the last two commits of a repository with the given renderer, returning stdout.
+    fn run_git_diff(dir: &Path, renderer: &str) -> String {
+        let output = Command::new(env!("CARGO_BIN_EXE_diffsitter"))
+            .current_dir(dir)
+            .args(["--no-config", "--renderer", renderer])
+            .args(["git", "--revisions", "HEAD~1", "HEAD"])
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1), "{output:?}");
+        String::from_utf8(output.stdout).unwrap()
+    }
+
+    /// Diff the test directories with the given renderer, returning stdout and stderr.
+    fn run_dir_diff(renderer: &str) -> (String, String) {
+        let output = Command::new(env!("CARGO_BIN_EXE_diffsitter"))
+            .args(["--no-config", "--renderer", renderer])
+            .arg(test_data_dir("old"))
+            .arg(test_data_dir("new"))
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1));
+        (
+            String::from_utf8(output.stdout).unwrap(),
+            String::from_utf8(output.stderr).unwrap(),
+        )
+    }
+
+    /// Files that can't be parsed are reported on stderr, so the SARIF log stays valid.
+    #[test]
+    fn dir_diff_sarif_with_unsupported_files() {
+        let (stdout, stderr) = run_dir_diff("sarif");
+        let log: serde_json::Value = serde_json::from_str(&stdout).unwrap();
+        let results = log["runs"][0]["results"].as_array().unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(stderr.contains("notes.txt differ"));
+        assert!(stderr.contains("removed.txt"));
+    }
+
+    #[test]
+    fn dir_diff_checkstyle_with_unsupported_files() {
+        let (stdout, stderr) = run_dir_diff("checkstyle");
+        assert!(stdout.starts_with("<?xml"));
+        assert!(stdout.trim_end().ends_with("</checkstyle>"));
+        assert_eq!(stdout.matches("<checkstyle ").count(), 1);
+        assert!(!stdout.contains("notes.txt"));
+        assert!(stderr.contains("notes.txt differ"));
+    }
+
+    #[test]
+    fn dir_diff_html_is_one_page() {
+        let (stdout, stderr) = run_dir_diff("html");
+        assert!(stdout.starts_with("<!DOCTYPE html>"));
+        assert!(stdout.trim_end().ends_with("</html>"));
+        assert_eq!(stdout.matches("<html").count(), 1);
+        assert!(!stdout.contains("notes.txt"));
+        assert!(stderr.contains("notes.txt differ"));
+    }
+
+    /// A file that can't be decoded is reported like an unsupported file, and the rest of the
+    /// directory is still diffed.
+    #[test]
+    fn dir_diff_with_undecodable_file() {
+        let dir = env::temp_dir().join(format!("diffsitter-undecodable-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        let (old, new) = (dir.join("old"), dir.join("new"));
+        fs::create_dir_all(&old).unwrap();
+        fs::create_dir_all(&new).unwrap();
+        fs::write(old.join("b.py"), "name = \"cafe\"\n").unwrap();
+        fs::write(new.join("b.py"), b"name = \"caf\xe9\"\n").unwrap();
+        fs::write(old.join("n.txt"), "first\n").unwrap();
+        fs::write(new.join("n.txt"), "second\n").unwrap();
+
+        let output = Command::new(env!("CARGO_BIN_EXE_diffsitter"))
+            .args(["--no-config", "--color", "off"])
+            .arg(&old)
+            .arg(&new)
+            .output()
+            .unwrap();
+        assert_eq!(output.status.code(), Some(1), "{output:?}");
+        let stdout = String::from_utf8(output.stdout).unwrap();
+        assert!(stdout.contains("b.py differ"), "{stdout}");
+        assert!(stdout.contains("n.txt differ"), "{stdout}");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// A patch of the changes between two revisions applies cleanly to the old revision.
+    #[test]
+    fn git_patch_applies() {
+        let dir = git_repo(
+            "patch",
+            "fn main() {\n    one();\n    two();\n}\n",
+            "fn main() {\n    uno();\n    two();\n    three();\n}\n",
+        );
+        let patch = run_git_diff(&dir, "patch");
+        assert!(patch.starts_with("--- a/src/main.rs\n+++ b/src/main.rs\n"));
+
+        fs::write(dir.join("change.patch"), patch).unwrap();
+        git(&dir, &["checkout", "-q", "HEAD~1"]);
+        git(&dir, &["apply", "--check", "change.patch"]);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    /// Locations refer to files in the repository rather than git's `a/` and `b/` names.
+    #[test]
+    fn git_locations_use_repository_paths() {
+        let dir = git_repo(
+            "locations",
+            "fn main() {\n    one();\n}\n",
+            "fn main() {\n    two();\n}\n",
+        );
+        let quickfix = run_git_diff(&dir, "quickfix");
+        assert_eq!(quickfix.lines().count(), 2);
+        assert!(quickfix
+            .lines()
+            .all(|record| record.starts_with("src/main.rs:2:")));
+
+        let log: serde_json::Value = serde_json::from_str(&run_git_diff(&dir, "sarif")).unwrap();
+        let uri = &log["runs"][0]["results"][0]["locations"][0]["physicalLocation"]
+            ["artifactLocation"]["uri"];
+        assert_eq!(uri, "src/main.rs");
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -3,11 +3,11 @@ mod tests {
     use insta::assert_snapshot;
     use libdiffsitter::{
         diff::{compute_edit_script, DocumentType, Hunk, RichHunks},
-        generate_ast_vector_data,
+        generate_ast_vector_data, generate_ast_vector_data_from_text,
         input_processing::{Entry, TreeSitterProcessor},
         parse::GrammarConfig,
     };
-    use std::path::PathBuf;
+    use std::{fs, path::PathBuf};
     use test_case::test_case;
 
     fn generate_snapshot_entries_string(entries: &[&Entry<'_>]) -> String {
@@ -117,4 +117,29 @@ mod tests {
         let snapshot_string = generate_snapshot_rich_hunks_string(diff_hunks);
         assert_snapshot!(snapshot_name, snapshot_string);
     }
+
+    /// Diffing text from memory should have the same results as reading the same text from files
+    #[test]
+    fn text_input_matches_file_input() {
+        let (path_a, path_b) = get_test_paths("short", "rust", "rs");
+        let config = GrammarConfig::default();
+        let processor = TreeSitterProcessor::default();
+
+        let file_data_a = generate_ast_vector_data(path_a.clone(), None, &config).unwrap();
+        let file_data_b = generate_ast_vector_data(path_b.clone(), None, &config).unwrap();
+        let text_a = fs::read_to_string(&path_a).unwrap();
+        let text_b = fs::read_to_string(&path_b).unwrap();
+        let text_data_a =
+            generate_ast_vector_data_from_text(&text_a, "a".into(), "rust", &config).unwrap();
+        let text_data_b =
+            generate_ast_vector_data_from_text(&text_b, "b".into(), "rust", &config).unwrap();
+
+        let file_vec_a = processor.process(&file_data_a.tree, &file_data_a.text);
+        let file_vec_b = processor.process(&file_data_b.tree, &file_data_b.text);
+        let text_vec_a = processor.process(&text_data_a.tree, &text_data_a.text);
+        let text_vec_b = processor.process(&text_data_b.tree, &text_data_b.text);
+        let file_hunks = compute_edit_script(&file_vec_a, &file_vec_b).unwrap();
+        let text_hunks = compute_edit_script(&text_vec_a, &text_vec_b).unwrap();
+        assert_eq!(file_hunks, text_hunks);
+    }
 }